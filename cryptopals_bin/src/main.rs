@@ -1,6 +1,11 @@
 extern crate cryptopals_lib as lib;
 
-use lib::{base64, blocks, cipher, hex, oracle, xor};
+extern crate rand;
+
+use lib::{
+    bank, base64, bignum, blocks, cipher, dh, dsa, hex, mac, md4, network, oracle, pkcs1, rsa,
+    sha1, srp, xor,
+};
 
 fn main() {
     println!("Set 1 - Challenge 1: {}", hex_decode_secret());
@@ -26,6 +31,64 @@ fn main() {
     println!("Set 2 - Challenge 11: {}", detection_oracle());
 
     println!("Set 2 - Challenge 12: {}", simple_ecb_decryption());
+
+    println!("Set 4 - Challenge 25: {}", break_random_access_ctr());
+
+    println!("Set 4 - Challenge 26: {}", ctr_bitflipping());
+
+    println!("Set 4 - Challenge 27: {}", recover_key_from_iv());
+
+    println!("Set 4 - Challenge 28: {}", sha1_keyed_mac());
+
+    println!("Set 4 - Challenge 29: {}", sha1_length_extension());
+
+    println!("Set 4 - Challenge 30: {}", md4_length_extension());
+
+    println!("Set 5 - Challenge 33: {}", diffie_hellman());
+
+    println!("Set 5 - Challenge 34: {}", dh_key_fixing());
+
+    println!("Set 5 - Challenge 35: {}", dh_malicious_g());
+
+    println!("Set 5 - Challenge 36: {}", secure_remote_password());
+
+    println!("Set 5 - Challenge 37: {}", srp_zero_key());
+
+    println!(
+        "Set 5 - Challenge 38: {}",
+        simplified_srp_dictionary_attack()
+    );
+
+    println!("Set 5 - Challenge 39: {}", implement_rsa());
+
+    println!("Set 5 - Challenge 40: {}", rsa_broadcast());
+
+    println!("Set 6 - Challenge 41: {}", unpadded_message_recovery());
+
+    println!("Set 6 - Challenge 42: {}", forge_e_equals_3_signature());
+
+    println!("Set 6 - Challenge 43: {}", dsa_key_from_nonce());
+
+    println!("Set 6 - Challenge 44: {}", dsa_repeated_nonce());
+
+    println!("Set 6 - Challenge 45: {}", dsa_parameter_tampering());
+
+    println!("Set 6 - Challenge 46: {}", rsa_parity_oracle());
+
+    println!("Set 6 - Challenge 47: {}", pkcs_padding_oracle(256));
+
+    println!("Set 6 - Challenge 48: {}", pkcs_padding_oracle(768));
+
+    println!("Set 7 - Challenge 49: {}", cbc_mac_forgery());
+    println!("Set 7 - Challenge 50: {}", cbc_mac_hash_collision());
+    println!(
+        "Set 7 - Challenge 51: {}",
+        compression_ratio_side_channel(cipher::EncryptionMode::CTR)
+    );
+    println!(
+        "Set 7 - Challenge 51: {}",
+        compression_ratio_side_channel(cipher::EncryptionMode::CBC)
+    );
 }
 
 fn hex_decode_secret() -> String {
@@ -130,6 +193,412 @@ fn simple_ecb_decryption() -> String {
     String::from_utf8(message).unwrap()
 }
 
+fn break_random_access_ctr() -> String {
+    let base64_decoder = |file: String| base64::decode(file.as_bytes());
+    let data = file_read_string("./challenge-data/7.txt", base64_decoder);
+    let plaintext = cipher::ecb_mode_decrypt(&data, b"YELLOW SUBMARINE");
+
+    let (secret, edit) = oracle::ctr_edit_oracle_generator(&plaintext);
+    let message = cipher::break_random_access_ctr(&secret, edit);
+
+    String::from_utf8(message).unwrap()
+}
+
+fn ctr_bitflipping() -> String {
+    let (encrypt, check_admin) = oracle::ctr_comment_oracle_generator();
+    let secret = cipher::ctr_bitflip(&encrypt, oracle::COMMENT_PREFIX.len(), b";admin=true;");
+
+    format!("admin={}", check_admin(&secret))
+}
+
+fn recover_key_from_iv() -> String {
+    let (encrypt, decrypt) = oracle::cbc_key_as_iv_oracle_generator();
+
+    match cipher::recover_key_from_iv(&encrypt, &decrypt) {
+        Some(key) => String::from_utf8(hex::encode(&key)).unwrap(),
+        None => String::from("Failed to recover key"),
+    }
+}
+
+fn sha1_keyed_mac() -> String {
+    let mac = mac::secret_prefix_mac::<sha1::Sha1>(b"YELLOW SUBMARINE", b"Ice, Ice, baby");
+    String::from_utf8(hex::encode(&mac)).unwrap()
+}
+
+fn sha1_length_extension() -> String {
+    let message = b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+    let (sign, verify) = oracle::secret_prefix_mac_oracle_generator::<sha1::Sha1>();
+
+    match mac::length_extension::<sha1::Sha1, _>(
+        &sign(message),
+        message,
+        b";admin=true",
+        0..64,
+        verify,
+    ) {
+        Some((_message, mac)) => String::from_utf8(hex::encode(&mac)).unwrap(),
+        None => String::from("Failed to forge mac"),
+    }
+}
+
+fn md4_length_extension() -> String {
+    let message = b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+    let (sign, verify) = oracle::secret_prefix_mac_oracle_generator::<md4::Md4>();
+
+    match mac::length_extension::<md4::Md4, _>(
+        &sign(message),
+        message,
+        b";admin=true",
+        0..64,
+        verify,
+    ) {
+        Some((_message, mac)) => String::from_utf8(hex::encode(&mac)).unwrap(),
+        None => String::from("Failed to forge mac"),
+    }
+}
+
+fn diffie_hellman() -> String {
+    let mut rng = rand::thread_rng();
+    let group = dh::Group::nist();
+
+    let alice = dh::KeyPair::generate(&group, &mut rng);
+    let bob = dh::KeyPair::generate(&group, &mut rng);
+    let s_a = dh::shared_secret(&group, &alice.private, &bob.public).unwrap();
+    let s_b = dh::shared_secret(&group, &bob.private, &alice.public).unwrap();
+
+    match s_a == s_b {
+        true => String::from_utf8(hex::encode(&dh::derive_key_sha1(&s_a))).unwrap(),
+        false => String::from("Shared secrets differ"),
+    }
+}
+
+fn dh_key_fixing() -> String {
+    let message = b"Rollin' in my 5.0";
+    let mut alice = dh::EchoClient::new(dh::Group::nist(), message, rand::thread_rng());
+    let mut bob = dh::EchoServer::new(rand::thread_rng());
+    let mut mallory = dh::EchoMitm::new(dh::EchoAttack::KeyFixing);
+
+    network::run(&mut alice, &mut bob, &mut mallory, 10);
+
+    match mallory.recovered.first() {
+        Some(message) => String::from_utf8(message.to_vec()).unwrap(),
+        None => String::from("Nothing recovered"),
+    }
+}
+
+fn dh_malicious_g() -> String {
+    let message = b"With my rag-top down so my hair can blow";
+    let attacks = [
+        dh::EchoAttack::GeneratorOne,
+        dh::EchoAttack::GeneratorP,
+        dh::EchoAttack::GeneratorPMinusOne,
+    ];
+
+    attacks
+        .iter()
+        .map(|attack| {
+            let mut alice =
+                dh::EchoClient::new(dh::Group::nist(), message, rand::thread_rng()).negotiating();
+            let mut bob = dh::EchoServer::new(rand::thread_rng());
+            let mut mallory = dh::EchoMitm::new(*attack);
+
+            network::run(&mut alice, &mut bob, &mut mallory, 10);
+
+            match mallory.recovered.first() {
+                Some(message) => String::from_utf8(message.to_vec()).unwrap(),
+                None => String::from("Nothing recovered"),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" / ")
+}
+
+fn secure_remote_password() -> String {
+    let group = dh::Group::nist();
+    let mut server = srp::SrpServer::new(group.clone(), rand::thread_rng());
+    server.register(b"user@example.com", b"correct horse battery staple");
+
+    let mut client = srp::SrpClient::new(
+        group,
+        b"user@example.com",
+        b"correct horse battery staple",
+        &mut rand::thread_rng(),
+    );
+    network::run(&mut client, &mut server, &mut network::Passthrough, 10);
+
+    format!("authenticated={}", client.authenticated == Some(true))
+}
+
+fn srp_zero_key() -> String {
+    let group = dh::Group::nist();
+    let mut server = srp::SrpServer::new(group.clone(), rand::thread_rng()).skip_validation();
+    server.register(b"user@example.com", b"correct horse battery staple");
+    let mut client = srp::ZeroKeyClient::new(&group, b"user@example.com", 2);
+
+    network::run(&mut client, &mut server, &mut network::Passthrough, 10);
+
+    format!("authenticated={}", client.authenticated == Some(true))
+}
+
+fn simplified_srp_dictionary_attack() -> String {
+    let group = dh::Group::nist();
+    let wordlist = srp::read_wordlist("./test-data/wordlist.txt").unwrap();
+    let mut client = srp::SrpClient::new(
+        group.clone(),
+        b"user@example.com",
+        b"sunshine",
+        &mut rand::thread_rng(),
+    );
+    let mut mallory = srp::DictionaryServer::new(group.clone());
+
+    network::run(&mut client, &mut mallory, &mut network::Passthrough, 10);
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+
+    match mallory
+        .captured
+        .and_then(|captured| srp::crack_password(&group, &captured, &wordlist, threads))
+    {
+        Some(password) => String::from_utf8(password).unwrap(),
+        None => String::from("Password not in wordlist"),
+    }
+}
+
+fn implement_rsa() -> String {
+    let key_pair = rsa::KeyPair::generate(1024, 3, &mut rand::thread_rng()).unwrap();
+    let ciphertext = key_pair.public.encrypt_bytes(b"RSA from scratch").unwrap();
+
+    String::from_utf8(key_pair.private.decrypt_bytes(&ciphertext).unwrap()).unwrap()
+}
+
+fn rsa_broadcast() -> String {
+    let mut rng = rand::thread_rng();
+    let message = b"Same message, three recipients";
+
+    let ciphertexts: Vec<_> = (0..3)
+        .map(|_| {
+            let key = rsa::KeyPair::generate(1024, 3, &mut rng).unwrap().public;
+            (key.encrypt_bytes(message).unwrap(), key)
+        })
+        .collect();
+
+    match rsa::hastad_broadcast(&ciphertexts) {
+        Some(m) => String::from_utf8(bignum::to_bytes_be(&m)).unwrap(),
+        None => String::from("No cube root"),
+    }
+}
+
+fn unpadded_message_recovery() -> String {
+    let mut rng = rand::thread_rng();
+    let key_pair = rsa::KeyPair::generate(1024, rsa::DEFAULT_EXPONENT, &mut rng).unwrap();
+    let mut server = oracle::DecryptOnceServer::new(key_pair);
+    let public = server.public_key().clone();
+
+    let ciphertext = public
+        .encrypt_bytes(b"{time: 1356304276, social: '555-55-5555'}")
+        .unwrap();
+    server.decrypt(&ciphertext).unwrap();
+
+    match rsa::recover_unpadded_message(&ciphertext, &public, |c| server.decrypt(c).ok(), &mut rng)
+    {
+        Some(m) => String::from_utf8(bignum::to_bytes_be(&m)).unwrap(),
+        None => String::from("Oracle refused"),
+    }
+}
+
+fn forge_e_equals_3_signature() -> String {
+    let key_pair = rsa::KeyPair::generate(1024, 3, &mut rand::thread_rng()).unwrap();
+    let algorithm = pkcs1::DigestAlgorithm::Sha1;
+
+    match pkcs1::forge_signature(&key_pair.public, algorithm, b"hi mom") {
+        Some(forged) => format!(
+            "broken verifier={}, strict verifier={}",
+            pkcs1::verify_broken(&key_pair.public, algorithm, b"hi mom", &forged),
+            pkcs1::verify(&key_pair.public, algorithm, b"hi mom", &forged)
+        ),
+        None => String::from("No room for a forgery"),
+    }
+}
+
+fn dsa_key_from_nonce() -> String {
+    let params = dsa::Parameters::challenge();
+    let public = bignum::from_hex(
+        concat!(
+            "84ad4719d044495496a3201c8ff484feb45b962e7302e56a392aee4abab3e4bdebf2955b4736012f21a0",
+            "8084056b19bcd7fee56048e004e44984e2f411788efdc837a0d2e5abb7b555039fd243ac01f0fb2ed1de",
+            "c568280ce678e931868d23eb095fde9d3779191b8c0299d6e07bbb283e6633451e535c45513b2d33c99e",
+            "a17",
+        )
+        .as_bytes(),
+    );
+    let message = b"For those that envy a MC it can be hazardous to your health\nSo be friendly, a matter of life and death, just like a etch-a-sketch\n";
+    let signature = dsa::Signature {
+        r: bignum::BigUint::parse_bytes(b"548099063082341131477253921760299949438196259240", 10)
+            .unwrap(),
+        s: bignum::BigUint::parse_bytes(b"857042759984254168557880549501802188789837994940", 10)
+            .unwrap(),
+    };
+
+    let hash = dsa::hash_message(message);
+    match dsa::recover_key_from_nonce_range(&params, &public, &hash, &signature, 0..1 << 16) {
+        Some(private) => String::from_utf8(dsa::fingerprint(&private)).unwrap(),
+        None => String::from("Nonce not in range"),
+    }
+}
+
+fn dsa_repeated_nonce() -> String {
+    let params = dsa::Parameters::challenge();
+    let public = bignum::from_hex(
+        concat!(
+            "5a6775b9e702bc7aa50eaab52b38a47184bc326a90b5868a13cfbc9f059115c581084ba629ab77fb1729",
+            "d58c5e0deb701883ea42259ffe590070bf4e978a8460fdd07f159b8ab2e9938e958737f15d5d51b8f25f",
+            "43b8556f558df896dc60fd7d49e7559ed7dbddfe84418d4cccf86a0ed0dc5106105094ff1faa91978214",
+            "270c",
+        )
+        .as_bytes(),
+    );
+    let messages = dsa::load_signed_messages("./challenge-data/44.txt").unwrap();
+
+    match dsa::recover_key_from_repeated_nonce(&params, &public, &messages) {
+        Some(private) => String::from_utf8(dsa::fingerprint(&private)).unwrap(),
+        None => String::from("No repeated nonce"),
+    }
+}
+
+fn dsa_parameter_tampering() -> String {
+    let mut rng = rand::thread_rng();
+    let challenge = dsa::Parameters::challenge();
+    let key_pair = dsa::KeyPair::generate(&challenge, &mut rng);
+    let params = dsa::Parameters::new(challenge.p.clone(), challenge.q, &challenge.p + 1u32);
+    let signature = dsa::forge_magic_signature(&params, &key_pair.public, &mut rng);
+
+    format!(
+        "\"Hello, world\"={}, \"Goodbye, world\"={}, validated={}",
+        dsa::verify(&params, &key_pair.public, b"Hello, world", &signature),
+        dsa::verify(&params, &key_pair.public, b"Goodbye, world", &signature),
+        dsa::verify_validated(&params, &key_pair.public, b"Hello, world", &signature)
+    )
+}
+
+fn rsa_parity_oracle() -> String {
+    let key_pair =
+        rsa::KeyPair::generate(1024, rsa::DEFAULT_EXPONENT, &mut rand::thread_rng()).unwrap();
+    let server = oracle::ParityServer::new(key_pair);
+    let public = server.public_key().clone();
+    let message = base64::decode(b"VGhhdCdzIHdoeSBJIGZvdW5kIHlvdSBkb24ndCBwbGF5IGFyb3VuZCB3aXRoIHRoZSBGdW5reSBDb2xkIE1lZGluYQ==");
+    let ciphertext = public.encrypt_bytes(&message).unwrap();
+
+    // Hollywood style: once the upper bound fits on a line, redraw it on stderr as it closes in
+    // on the plaintext.
+    let hollywood = |upper: &bignum::BigUint| {
+        let bytes = bignum::to_bytes_be(upper);
+        if bytes.len() <= 72 {
+            let text: String = bytes
+                .iter()
+                .map(|&b| {
+                    if b.is_ascii_graphic() || b == b' ' {
+                        b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            eprint!("\r{:<72}", text);
+        }
+    };
+    let recovered = rsa::parity_attack(&ciphertext, &public, |c| server.is_even(c).ok(), hollywood);
+    eprintln!();
+
+    match recovered {
+        Some(plaintext) => String::from_utf8_lossy(&bignum::to_bytes_be(&plaintext)).into_owned(),
+        None => String::from("Oracle stopped answering"),
+    }
+}
+
+fn pkcs_padding_oracle(bits: u64) -> String {
+    let mut rng = rand::thread_rng();
+    let key_pair = rsa::KeyPair::generate(bits, 3, &mut rng).unwrap();
+    let server = oracle::PaddingServer::new(key_pair, pkcs1::PaddingCheck::Prefix);
+    let public = server.public_key().clone();
+    let ciphertext = pkcs1::encrypt(&public, b"kick it, CC", &mut rng).unwrap();
+
+    match pkcs1::padding_oracle_attack(&ciphertext, &public, |c| server.is_conforming(c).ok())
+        .and_then(|recovery| Some((pkcs1::unpad(&recovery.block)?, recovery.queries)))
+    {
+        Some((message, queries)) => format!(
+            "{} ({} oracle queries)",
+            String::from_utf8_lossy(&message),
+            queries
+        ),
+        None => String::from("Oracle stopped answering"),
+    }
+}
+
+fn cbc_mac_forgery() -> String {
+    use bank::{BankClient, BankServer, ExtensionMitm, Protocol, SenderMitm};
+    use rand::Rng;
+
+    let key: Vec<u8> = (0..16).map(|_| rand::thread_rng().gen::<u8>()).collect();
+    let (victim, attacker) = (2, 3);
+
+    let mut server = BankServer::new(&key, Protocol::ChosenIv, &[(victim, 1_000_000)]);
+    let mut client = BankClient::new(&key, Protocol::ChosenIv, attacker, rand::thread_rng());
+    client.transfer(&[(attacker, 1_000_000)]);
+    network::run(&mut client, &mut server, &mut SenderMitm::new(victim), 10);
+    let chosen_iv = server.balance(attacker);
+
+    let mut server = BankServer::new(&key, Protocol::FixedIv, &[(victim, 2_000_000)]);
+    let mut client = BankClient::new(&key, Protocol::FixedIv, victim, rand::thread_rng());
+    for amount in 1..=20 {
+        client.transfer(&[(4, amount), (5, amount)]);
+    }
+    let own_client = BankClient::new(&key, Protocol::FixedIv, attacker, rand::thread_rng());
+    let mut mallory = ExtensionMitm::new(own_client, 1_000_000);
+    network::run(&mut client, &mut server, &mut mallory, 100);
+
+    format!(
+        "attacker balance after IV forgery={}, after length extension={}",
+        chosen_iv,
+        server.balance(attacker)
+    )
+}
+
+fn cbc_mac_hash_collision() -> String {
+    let snippet = b"alert('MZA who was that?');\n";
+    let prefix = b"alert('Ayo, the Wu is back!');//";
+    let target = mac::cbc_mac_hash(snippet);
+
+    let forged = mac::cbc_mac_collision_where::<cipher::Aes128, _>(
+        mac::CBC_MAC_HASH_KEY,
+        &[0; 16],
+        prefix,
+        &target,
+        b' ',
+        |forged| !mac::ends_line_comment(&forged[prefix.len()..]),
+    )
+    .unwrap();
+
+    format!(
+        "{:?} hashes to {}",
+        String::from_utf8_lossy(&forged),
+        String::from_utf8(hex::encode(&mac::cbc_mac_hash(&forged))).unwrap()
+    )
+}
+
+fn compression_ratio_side_channel(mode: cipher::EncryptionMode) -> String {
+    let alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/=";
+    let label = mode.to_string();
+    let oracle = oracle::compression_oracle_generator(oracle::SESSION_ID, mode);
+
+    match cipher::compression_attack(oracle, b"sessionid=", alphabet, b'\n') {
+        Some(session_id) => format!(
+            "{} sessionid={}",
+            label,
+            String::from_utf8_lossy(&session_id)
+        ),
+        None => format!("{} failed to recover the session id", label),
+    }
+}
+
 fn hex_decode(bytes: &[u8]) -> String {
     let secret = hex::decode(bytes);
     String::from_utf8(secret).unwrap()
@@ -0,0 +1,539 @@
+use super::cipher::{Aes128, BlockCipher};
+use super::mac::{cbc_mac, forge_cbc_mac_extension, forge_cbc_mac_iv, verify_cbc_mac};
+use super::network::{Action, Direction, Interceptor, Party};
+use rand::Rng;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+const BLOCK_SIZE: usize = Aes128::BLOCK_SIZE;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Protocol {
+    // `from=#&to=#&amount=#`, MACed under a random IV that travels with the message.
+    ChosenIv,
+    // `from=#&tx_list=#:#(;#:#)*`, MACed under a zero IV.
+    FixedIv,
+}
+
+impl Protocol {
+    fn iv(self, iv: Option<&[u8]>) -> Option<Vec<u8>> {
+        match (self, iv) {
+            (Protocol::ChosenIv, Some(iv)) if iv.len() == BLOCK_SIZE => Some(iv.to_vec()),
+            (Protocol::FixedIv, None) => Some(vec![0; BLOCK_SIZE]),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BankMessage {
+    // `iv` is only sent with `Protocol::ChosenIv`.
+    Request {
+        message: Vec<u8>,
+        iv: Option<Vec<u8>>,
+        mac: Vec<u8>,
+    },
+    Accepted,
+    Rejected,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transfer {
+    pub from: u32,
+    pub to: u32,
+    pub amount: u64,
+}
+
+// The single transfer protocol can't carry more than one transfer.
+pub fn encode_request(protocol: Protocol, from: u32, transfers: &[(u32, u64)]) -> Option<Vec<u8>> {
+    match (protocol, transfers) {
+        (Protocol::ChosenIv, [(to, amount)]) => {
+            Some(format!("from={}&to={}&amount={}", from, to, amount).into_bytes())
+        }
+        (Protocol::ChosenIv, _) => None,
+        (Protocol::FixedIv, _) => {
+            let tx_list: Vec<String> = transfers
+                .iter()
+                .map(|(to, amount)| format!("{}:{}", to, amount))
+                .collect();
+            Some(format!("from={}&tx_list={}", from, tx_list.join(";")).into_bytes())
+        }
+    }
+}
+
+// Lenient like most query string parsers: fields without an `=` and transactions that don't
+// parse are skipped rather than failing the request.
+pub fn parse_request(protocol: Protocol, message: &[u8]) -> Option<Vec<Transfer>> {
+    let fields: Vec<(&[u8], &[u8])> = message
+        .split(|&b| b == b'&')
+        .filter_map(|field| {
+            let split_at = field.iter().position(|&b| b == b'=')?;
+            Some((&field[..split_at], &field[split_at + 1..]))
+        })
+        .collect();
+    let field = |name: &[u8]| {
+        fields
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| *value)
+    };
+
+    let from = number(field(b"from")?)?;
+    let transfers = match protocol {
+        Protocol::ChosenIv => vec![(number(field(b"to")?)?, number(field(b"amount")?)?)],
+        Protocol::FixedIv => field(b"tx_list")?
+            .split(|&b| b == b';')
+            .filter_map(|transaction| {
+                let split_at = transaction.iter().position(|&b| b == b':')?;
+                let to = number(&transaction[..split_at])?;
+                let amount = number(&transaction[split_at + 1..])?;
+                Some((to, amount))
+            })
+            .collect(),
+    };
+
+    Some(
+        transfers
+            .into_iter()
+            .map(|(to, amount)| Transfer { from, to, amount })
+            .collect(),
+    )
+}
+
+fn number<T: FromStr>(bytes: &[u8]) -> Option<T> {
+    std::str::from_utf8(bytes).ok()?.parse().ok()
+}
+
+// The web app: it holds the MAC key and signs requests for the account that's logged in.
+pub struct BankClient<R: Rng> {
+    rng: R,
+    key: Vec<u8>,
+    protocol: Protocol,
+    account: u32,
+    outbox: Vec<BankMessage>,
+    pub responses: Vec<bool>,
+}
+
+impl<R: Rng> BankClient<R> {
+    pub fn new(key: &[u8], protocol: Protocol, account: u32, rng: R) -> Self {
+        Self {
+            rng,
+            key: key.to_vec(),
+            protocol,
+            account,
+            outbox: Vec::new(),
+            responses: Vec::new(),
+        }
+    }
+
+    pub fn account(&self) -> u32 {
+        self.account
+    }
+
+    pub fn sign(&mut self, transfers: &[(u32, u64)]) -> Option<BankMessage> {
+        let message = encode_request(self.protocol, self.account, transfers)?;
+        let iv = match self.protocol {
+            Protocol::ChosenIv => Some((0..BLOCK_SIZE).map(|_| self.rng.gen::<u8>()).collect()),
+            Protocol::FixedIv => None,
+        };
+        let mac_iv = self.protocol.iv(iv.as_deref())?;
+        let mac = cbc_mac::<Aes128>(&self.key, &mac_iv, &message);
+
+        Some(BankMessage::Request { message, iv, mac })
+    }
+
+    // Queues a signed request to go out when the client starts.
+    pub fn transfer(&mut self, transfers: &[(u32, u64)]) -> bool {
+        match self.sign(transfers) {
+            Some(request) => {
+                self.outbox.push(request);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<R: Rng> Party<BankMessage> for BankClient<R> {
+    fn start(&mut self) -> Vec<BankMessage> {
+        self.outbox.drain(..).collect()
+    }
+
+    fn receive(&mut self, message: BankMessage) -> Vec<BankMessage> {
+        match message {
+            BankMessage::Accepted => self.responses.push(true),
+            BankMessage::Rejected => self.responses.push(false),
+            BankMessage::Request { .. } => {}
+        }
+
+        vec![]
+    }
+}
+
+// The API server: trusts any request with a valid MAC, as only the web app has the key.
+pub struct BankServer {
+    key: Vec<u8>,
+    protocol: Protocol,
+    pub balances: HashMap<u32, u64>,
+    pub ledger: Vec<Transfer>,
+}
+
+impl BankServer {
+    pub fn new(key: &[u8], protocol: Protocol, balances: &[(u32, u64)]) -> Self {
+        Self {
+            key: key.to_vec(),
+            protocol,
+            balances: balances.iter().cloned().collect(),
+            ledger: Vec::new(),
+        }
+    }
+
+    pub fn balance(&self, account: u32) -> u64 {
+        self.balances.get(&account).cloned().unwrap_or(0)
+    }
+
+    // Applies every transfer in the request, or none of them if the sender can't cover all.
+    fn process(&mut self, message: &[u8], iv: Option<&[u8]>, mac: &[u8]) -> bool {
+        let iv = match self.protocol.iv(iv) {
+            Some(iv) => iv,
+            None => return false,
+        };
+        if !verify_cbc_mac::<Aes128>(&self.key, &iv, message, mac) {
+            return false;
+        }
+
+        let transfers = match parse_request(self.protocol, message) {
+            Some(transfers) if !transfers.is_empty() => transfers,
+            _ => return false,
+        };
+        let from = transfers[0].from;
+        let total = transfers
+            .iter()
+            .try_fold(0u64, |total, transfer| total.checked_add(transfer.amount));
+        match total {
+            Some(total) if total <= self.balance(from) => {}
+            _ => return false,
+        }
+
+        // Works on a copy, so a credit that would overflow leaves every balance untouched.
+        let mut balances = self.balances.clone();
+        for transfer in &transfers {
+            *balances.entry(transfer.from).or_insert(0) -= transfer.amount;
+            let to = balances.entry(transfer.to).or_insert(0);
+            match to.checked_add(transfer.amount) {
+                Some(balance) => *to = balance,
+                None => return false,
+            }
+        }
+        self.balances = balances;
+        self.ledger.extend(transfers);
+
+        true
+    }
+}
+
+impl Party<BankMessage> for BankServer {
+    fn receive(&mut self, message: BankMessage) -> Vec<BankMessage> {
+        match message {
+            BankMessage::Request { message, iv, mac } => {
+                match self.process(&message, iv.as_deref(), &mac) {
+                    true => vec![BankMessage::Accepted],
+                    false => vec![BankMessage::Rejected],
+                }
+            }
+            _ => vec![],
+        }
+    }
+}
+
+// Challenge 49, first half: the attacker's own signed request, with the sender swapped for
+// `victim` and the IV adjusted to match. Only works while both ids have as many digits, so
+// the change stays inside the first block.
+pub fn forge_sender(request: &BankMessage, victim: u32) -> Option<BankMessage> {
+    let (message, iv, mac) = match request {
+        BankMessage::Request {
+            message,
+            iv: Some(iv),
+            mac,
+        } => (message, iv, mac),
+        _ => return None,
+    };
+
+    let sender_end = message.iter().position(|&b| b == b'&')?;
+    if !message.starts_with(b"from=") {
+        return None;
+    }
+    let forged = [
+        format!("from={}", victim).as_bytes(),
+        &message[sender_end..],
+    ]
+    .concat();
+    let forged_iv = forge_cbc_mac_iv(iv, message, &forged)?;
+
+    Some(BankMessage::Request {
+        message: forged,
+        iv: Some(forged_iv),
+        mac: mac.clone(),
+    })
+}
+
+// Rewrites the attacker's own outgoing requests so they're sent from `victim`.
+pub struct SenderMitm {
+    victim: u32,
+    pub forged: Vec<BankMessage>,
+}
+
+impl SenderMitm {
+    pub fn new(victim: u32) -> Self {
+        Self {
+            victim,
+            forged: Vec::new(),
+        }
+    }
+}
+
+impl Interceptor<BankMessage> for SenderMitm {
+    fn intercept(&mut self, direction: Direction, message: &BankMessage) -> Action<BankMessage> {
+        if direction != Direction::ToServer {
+            return Action::Forward;
+        }
+
+        match forge_sender(message, self.victim) {
+            Some(forged) => {
+                self.forged.push(forged.clone());
+                Action::Replace(forged)
+            }
+            None => Action::Forward,
+        }
+    }
+}
+
+// Challenge 49, second half: glues a request the attacker signed for themselves onto a
+// captured one, so the result carries the attacker's MAC and the victim's `from`. The
+// attacker's first block turns into garbage, which breaks the request if it happens to hold
+// an `&`, so this gives up and waits for the next capture in that case.
+pub fn forge_extension<R: Rng>(
+    captured: &BankMessage,
+    attacker: &mut BankClient<R>,
+    amount: u64,
+) -> Option<BankMessage> {
+    let (message, mac) = match captured {
+        BankMessage::Request {
+            message,
+            iv: None,
+            mac,
+        } => (message, mac),
+        _ => return None,
+    };
+
+    // The first block is the one that gets garbled, so lead with a throwaway zero transfer.
+    let account = attacker.account();
+    let own = match attacker.sign(&[(account, 0), (account, amount)])? {
+        BankMessage::Request { message, mac, .. } => (message, mac),
+        _ => return None,
+    };
+
+    let iv = [0; BLOCK_SIZE];
+    let forged = forge_cbc_mac_extension(&iv, message, mac, &own.0)?;
+    let glue_start = forged.len() - own.0.len();
+    if forged[glue_start..glue_start + BLOCK_SIZE].contains(&b'&') {
+        return None;
+    }
+
+    Some(BankMessage::Request {
+        message: forged,
+        iv: None,
+        mac: own.1,
+    })
+}
+
+// Watches the wire for other customers' requests and slips a forged one in behind the first
+// that can be extended.
+pub struct ExtensionMitm<R: Rng> {
+    attacker: BankClient<R>,
+    amount: u64,
+    pub captured: Vec<BankMessage>,
+    pub forged: Option<BankMessage>,
+}
+
+impl<R: Rng> ExtensionMitm<R> {
+    pub fn new(attacker: BankClient<R>, amount: u64) -> Self {
+        Self {
+            attacker,
+            amount,
+            captured: Vec::new(),
+            forged: None,
+        }
+    }
+}
+
+impl<R: Rng> Interceptor<BankMessage> for ExtensionMitm<R> {
+    fn intercept(&mut self, direction: Direction, message: &BankMessage) -> Action<BankMessage> {
+        if direction != Direction::ToServer || self.forged.is_some() {
+            return Action::Forward;
+        }
+        self.captured.push(message.clone());
+
+        match forge_extension(message, &mut self.attacker, self.amount) {
+            Some(forged) => {
+                self.forged = Some(forged.clone());
+                Action::Inject(forged)
+            }
+            None => Action::Forward,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::network;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    const KEY: &[u8] = b"YELLOW SUBMARINE";
+
+    fn client(protocol: Protocol, account: u32) -> BankClient<StdRng> {
+        BankClient::new(
+            KEY,
+            protocol,
+            account,
+            StdRng::seed_from_u64(account as u64),
+        )
+    }
+
+    mod requests {
+        use super::*;
+
+        #[test]
+        fn encode_and_parse() {
+            let single = encode_request(Protocol::ChosenIv, 1, &[(2, 30)]).unwrap();
+            let many = encode_request(Protocol::FixedIv, 1, &[(2, 30), (3, 40)]).unwrap();
+
+            assert_eq!(b"from=1&to=2&amount=30".to_vec(), single);
+            assert_eq!(b"from=1&tx_list=2:30;3:40".to_vec(), many);
+            assert_eq!(None, encode_request(Protocol::ChosenIv, 1, &[]));
+            assert_eq!(
+                Some(vec![Transfer {
+                    from: 1,
+                    to: 2,
+                    amount: 30
+                }]),
+                parse_request(Protocol::ChosenIv, &single)
+            );
+            assert_eq!(2, parse_request(Protocol::FixedIv, &many).unwrap().len());
+        }
+
+        #[test]
+        fn parser_skips_garbage() {
+            let transfers = parse_request(
+                Protocol::FixedIv,
+                b"from=1&junk&tx_list=2:30;\xff\x00:1;3:40",
+            )
+            .unwrap();
+
+            assert_eq!(
+                vec![2, 3],
+                transfers.iter().map(|t| t.to).collect::<Vec<_>>()
+            );
+            assert_eq!(None, parse_request(Protocol::FixedIv, b"tx_list=2:30"));
+            assert_eq!(None, parse_request(Protocol::ChosenIv, b"from=1&to=2"));
+        }
+    }
+
+    mod server {
+        use super::*;
+
+        #[test]
+        fn executes_signed_requests() {
+            for protocol in [Protocol::ChosenIv, Protocol::FixedIv].iter() {
+                let mut alice = client(*protocol, 1);
+                let mut server = BankServer::new(KEY, *protocol, &[(1, 100)]);
+                assert!(alice.transfer(&[(2, 30)]));
+                assert!(alice.transfer(&[(2, 80)]));
+
+                network::run(&mut alice, &mut server, &mut network::Passthrough, 10);
+                assert_eq!(vec![true, false], alice.responses);
+                assert_eq!(70, server.balance(1));
+                assert_eq!(30, server.balance(2));
+            }
+        }
+
+        #[test]
+        fn rejects_bad_macs_and_ivs() {
+            let mut server = BankServer::new(KEY, Protocol::ChosenIv, &[(1, 100)]);
+            let request = client(Protocol::ChosenIv, 1).sign(&[(2, 30)]).unwrap();
+            let (message, iv, mac) = match request {
+                BankMessage::Request { message, iv, mac } => (message, iv, mac),
+                _ => unreachable!(),
+            };
+
+            assert!(!server.process(&message, None, &mac));
+            assert!(!server.process(&message, iv.as_deref(), &[0; 16]));
+            assert!(!server.process(b"from=1&to=3&amount=30", iv.as_deref(), &mac));
+            assert!(server.process(&message, iv.as_deref(), &mac));
+        }
+
+        #[test]
+        fn rejects_overflowing_credits() {
+            let mut alice = client(Protocol::FixedIv, 1);
+            let mut server =
+                BankServer::new(KEY, Protocol::FixedIv, &[(1, 100), (3, u64::MAX - 10)]);
+            assert!(alice.transfer(&[(2, 30), (3, 20)]));
+            assert!(alice.transfer(&[(2, 30), (3, 10)]));
+
+            network::run(&mut alice, &mut server, &mut network::Passthrough, 10);
+            assert_eq!(vec![false, true], alice.responses);
+            assert_eq!(60, server.balance(1));
+            assert_eq!(30, server.balance(2));
+            assert_eq!(u64::MAX, server.balance(3));
+            assert_eq!(2, server.ledger.len());
+        }
+    }
+
+    mod forgery {
+        use super::*;
+
+        #[test]
+        fn iv_forgery_moves_the_victims_money() {
+            let mut attacker = client(Protocol::ChosenIv, 3);
+            let mut server = BankServer::new(KEY, Protocol::ChosenIv, &[(2, 1_000_000)]);
+            let mut mallory = SenderMitm::new(2);
+            attacker.transfer(&[(3, 1_000_000)]);
+
+            network::run(&mut attacker, &mut server, &mut mallory, 10);
+            assert_eq!(1, mallory.forged.len());
+            assert_eq!(vec![true], attacker.responses);
+            assert_eq!(0, server.balance(2));
+            assert_eq!(1_000_000, server.balance(3));
+        }
+
+        #[test]
+        fn sender_must_keep_its_length() {
+            let request = client(Protocol::ChosenIv, 3).sign(&[(3, 100)]).unwrap();
+
+            assert!(forge_sender(&request, 4).is_some());
+            assert_eq!(None, forge_sender(&request, 42));
+        }
+
+        #[test]
+        fn extension_appends_the_attackers_transfer() {
+            let mut victim = client(Protocol::FixedIv, 2);
+            let mut server = BankServer::new(KEY, Protocol::FixedIv, &[(2, 2_000_000)]);
+            let mut mallory = ExtensionMitm::new(client(Protocol::FixedIv, 3), 1_000_000);
+            for amount in 100..105 {
+                victim.transfer(&[(5, amount), (6, amount)]);
+            }
+
+            network::run(&mut victim, &mut server, &mut mallory, 20);
+            assert!(mallory.forged.is_some());
+            assert_eq!(1_000_000, server.balance(3));
+            assert!(server.ledger.iter().any(|transfer| transfer
+                == &Transfer {
+                    from: 2,
+                    to: 3,
+                    amount: 1_000_000
+                }));
+        }
+    }
+}
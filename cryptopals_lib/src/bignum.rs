@@ -0,0 +1,385 @@
+extern crate num_bigint;
+extern crate num_integer;
+extern crate num_traits;
+
+use super::hex;
+use num_integer::Integer;
+use num_traits::{One, Signed, Zero};
+use rand::Rng;
+
+pub use num_bigint::{BigInt, BigUint, RandBigInt, Sign, ToBigInt};
+
+pub fn from_hex(bytes: &[u8]) -> BigUint {
+    BigUint::from_bytes_be(&hex::decode(bytes))
+}
+
+pub fn to_hex(n: &BigUint) -> Vec<u8> {
+    hex::encode(&n.to_bytes_be())
+}
+
+pub fn from_bytes_be(bytes: &[u8]) -> BigUint {
+    BigUint::from_bytes_be(bytes)
+}
+
+pub fn to_bytes_be(n: &BigUint) -> Vec<u8> {
+    n.to_bytes_be()
+}
+
+// Left pads with zeros, for fixed width encodings like an RSA block of the modulus length.
+pub fn to_bytes_be_padded(n: &BigUint, length: usize) -> Vec<u8> {
+    let bytes = n.to_bytes_be();
+    if bytes.len() > length {
+        panic!("{} bytes don't fit into {} bytes.", bytes.len(), length);
+    }
+
+    [vec![0; length - bytes.len()], bytes].concat()
+}
+
+pub fn divmod(a: &BigUint, b: &BigUint) -> (BigUint, BigUint) {
+    a.div_rem(b)
+}
+
+pub fn modpow(base: &BigUint, exponent: &BigUint, modulus: &BigUint) -> BigUint {
+    base.modpow(exponent, modulus)
+}
+
+pub fn gcd(a: &BigUint, b: &BigUint) -> BigUint {
+    a.gcd(b)
+}
+
+// Returns (g, x, y) with a * x + b * y = g = gcd(a, b).
+pub fn egcd(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
+    let (mut old_r, mut r) = (a.clone(), b.clone());
+    let (mut old_x, mut x) = (BigInt::one(), BigInt::zero());
+    let (mut old_y, mut y) = (BigInt::zero(), BigInt::one());
+
+    while !r.is_zero() {
+        let quotient = old_r.div_floor(&r);
+
+        let next_r = &old_r - &quotient * &r;
+        old_r = std::mem::replace(&mut r, next_r);
+        let next_x = &old_x - &quotient * &x;
+        old_x = std::mem::replace(&mut x, next_x);
+        let next_y = &old_y - &quotient * &y;
+        old_y = std::mem::replace(&mut y, next_y);
+    }
+
+    if old_r.is_negative() {
+        return (-old_r, -old_x, -old_y);
+    }
+
+    (old_r, old_x, old_y)
+}
+
+pub fn modinv(a: &BigUint, modulus: &BigUint) -> Option<BigUint> {
+    let modulus = modulus.to_bigint().unwrap();
+    let (g, x, _y) = egcd(&a.to_bigint().unwrap(), &modulus);
+
+    if !g.is_one() {
+        return None;
+    }
+
+    x.mod_floor(&modulus).to_biguint()
+}
+
+// Returns the unique x mod N = m_0 * m_1 * ... with x = r_i mod m_i, along with N. The moduli
+// must be pairwise coprime.
+pub fn crt(residues: &[BigUint], moduli: &[BigUint]) -> Option<(BigUint, BigUint)> {
+    if residues.len() != moduli.len() || moduli.is_empty() {
+        return None;
+    }
+
+    let product = moduli.iter().product::<BigUint>();
+    let mut x = BigUint::zero();
+    for (residue, modulus) in residues.iter().zip(moduli.iter()) {
+        let rest = &product / modulus;
+        let inverse = modinv(&(&rest % modulus), modulus)?;
+        x += residue * &rest * inverse;
+    }
+
+    Some((x % &product, product))
+}
+
+// Floor of the k-th root.
+pub fn iroot(n: &BigUint, k: u32) -> BigUint {
+    n.nth_root(k)
+}
+
+// The k-th root only if `n` is a perfect k-th power.
+pub fn exact_root(n: &BigUint, k: u32) -> Option<BigUint> {
+    let root = iroot(n, k);
+    match root.pow(k) == *n {
+        true => Some(root),
+        false => None,
+    }
+}
+
+pub fn isqrt(n: &BigUint) -> BigUint {
+    n.sqrt()
+}
+
+pub fn bits(n: &BigUint) -> u64 {
+    n.bits()
+}
+
+pub fn bit(n: &BigUint, index: u64) -> bool {
+    ((n >> index as usize) & BigUint::one()).is_one()
+}
+
+pub fn set_bit(n: &BigUint, index: u64, value: bool) -> BigUint {
+    let mask = BigUint::one() << index as usize;
+
+    match (bit(n, index), value) {
+        (false, true) => n | mask,
+        (true, false) => n ^ mask,
+        _ => n.clone(),
+    }
+}
+
+pub const MILLER_RABIN_ROUNDS: usize = 40;
+
+const SMALL_PRIMES: [u32; 25] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+];
+
+pub fn is_probable_prime<R: Rng>(n: &BigUint, rounds: usize, rng: &mut R) -> bool {
+    for &small in SMALL_PRIMES.iter() {
+        let small = BigUint::from(small);
+        if *n == small {
+            return true;
+        }
+        if (n % &small).is_zero() {
+            return false;
+        }
+    }
+    if *n < BigUint::from(2u32) {
+        return false;
+    }
+
+    // n - 1 = 2^s * d with d odd
+    let n_minus_one = n - 1u32;
+    let s = n_minus_one.trailing_zeros().unwrap_or(0);
+    let d = &n_minus_one >> s as usize;
+    let two = BigUint::from(2u32);
+
+    'witness: for _ in 0..rounds {
+        let a = rng.gen_biguint_range(&two, &n_minus_one);
+        let mut x = a.modpow(&d, n);
+        if x.is_one() || x == n_minus_one {
+            continue;
+        }
+
+        for _ in 1..s {
+            x = x.modpow(&two, n);
+            if x == n_minus_one {
+                continue 'witness;
+            }
+        }
+
+        return false;
+    }
+
+    true
+}
+
+// A random prime of exactly `bits` bits. The top two bits are set so that the product of
+// two such primes has exactly twice as many bits.
+pub fn generate_prime<R: Rng>(bits: u64, rng: &mut R) -> BigUint {
+    if bits < 3 {
+        panic!("Can't generate a {} bit prime.", bits);
+    }
+
+    loop {
+        let candidate = rng.gen_biguint(bits);
+        let candidate = set_bit(&candidate, bits - 1, true);
+        let candidate = set_bit(&candidate, bits - 2, true);
+        let candidate = set_bit(&candidate, 0, true);
+
+        if is_probable_prime(&candidate, MILLER_RABIN_ROUNDS, rng) {
+            return candidate;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn big(n: u64) -> BigUint {
+        BigUint::from(n)
+    }
+
+    fn signed(n: i64) -> BigInt {
+        BigInt::from(n)
+    }
+
+    mod conversions {
+        use super::*;
+
+        #[test]
+        fn hex_round_trip() {
+            let n = from_hex(b"ffffffffffffffffc90fdaa22168c234c4c6628b80dc1cd1");
+
+            assert_eq!(
+                b"ffffffffffffffffc90fdaa22168c234c4c6628b80dc1cd1".to_vec(),
+                to_hex(&n)
+            );
+            assert_eq!(big(0xabc), from_hex(b"ABC"));
+            assert_eq!(b"00".to_vec(), to_hex(&big(0)));
+        }
+
+        #[test]
+        fn bytes_round_trip() {
+            let n = big(0x0102_0304);
+
+            assert_eq!(vec![1, 2, 3, 4], to_bytes_be(&n));
+            assert_eq!(n, from_bytes_be(&[0, 0, 1, 2, 3, 4]));
+            assert_eq!(vec![0, 0, 1, 2, 3, 4], to_bytes_be_padded(&n, 6));
+        }
+    }
+
+    mod arithmetic {
+        use super::*;
+
+        #[test]
+        fn _divmod() {
+            assert_eq!((big(33), big(1)), divmod(&big(100), &big(3)));
+        }
+
+        #[test]
+        fn _modpow() {
+            assert_eq!(big(445), modpow(&big(4), &big(13), &big(497)));
+
+            let p = from_hex(b"ffffffffffffffffc90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74020bbea63b139b22514a08798e3404ddef9519b3cd3a431b302b0a6df25f14374fe1356d6d51c245e485b576625e7ec6f44c42e9a637ed6b0bff5cb6f406b7edee386bfb5a899fa5ae9f24117c4b1fe649286651ece45b3dc2007cb8a163bf0598da48361c55d39a69163fa8fd24cf5f83655d23dca3ad961c62f356208552bb9ed529077096966d670c354e4abc9804f1746c08ca237327ffffffffffffffff");
+            let a = modpow(&big(2), &big(1234), &p);
+            let b = modpow(&big(2), &big(5678), &p);
+
+            assert_eq!(modpow(&a, &big(5678), &p), modpow(&b, &big(1234), &p));
+        }
+
+        #[test]
+        fn _gcd() {
+            assert_eq!(big(6), gcd(&big(54), &big(24)));
+            assert_eq!(big(1), gcd(&big(17), &big(3120)));
+        }
+
+        #[test]
+        fn _egcd() {
+            let (g, x, y) = egcd(&signed(240), &signed(46));
+
+            assert_eq!(signed(2), g);
+            assert_eq!(signed(2), signed(240) * x + signed(46) * y);
+        }
+
+        #[test]
+        fn _modinv() {
+            assert_eq!(Some(big(2753)), modinv(&big(17), &big(3120)));
+            assert_eq!(Some(big(1969)), modinv(&big(42), &big(2017)));
+            assert_eq!(None, modinv(&big(6), &big(9)));
+        }
+
+        #[test]
+        fn _iroot() {
+            assert_eq!(big(3), iroot(&big(27), 3));
+            assert_eq!(big(3), iroot(&big(63), 3));
+            assert_eq!(big(4), iroot(&big(64), 3));
+            assert_eq!(big(12), isqrt(&big(150)));
+
+            let cube = from_hex(b"123456789abcdef0123456789abcdef").pow(3u32);
+            assert_eq!(
+                from_hex(b"123456789abcdef0123456789abcdef"),
+                iroot(&cube, 3)
+            );
+        }
+    }
+
+    mod crt {
+        use super::*;
+
+        #[test]
+        fn sunzi() {
+            let residues = [big(2), big(3), big(2)];
+            let moduli = [big(3), big(5), big(7)];
+
+            assert_eq!(Some((big(23), big(105))), crt(&residues, &moduli));
+        }
+
+        #[test]
+        fn rejects_bad_moduli() {
+            assert_eq!(None, crt(&[big(1), big(2)], &[big(4), big(6)]));
+            assert_eq!(None, crt(&[big(1)], &[big(4), big(5)]));
+            assert_eq!(None, crt(&[], &[]));
+        }
+
+        #[test]
+        fn exact_roots() {
+            let n = from_hex(b"123456789abcdef0123456789abcdef");
+
+            assert_eq!(Some(n.clone()), exact_root(&n.pow(5u32), 5));
+            assert_eq!(None, exact_root(&(n.pow(5u32) + 1u32), 5));
+        }
+    }
+
+    mod bit_access {
+        use super::*;
+
+        #[test]
+        fn read_and_write_bits() {
+            let n = big(0b1010);
+
+            assert_eq!(4, bits(&n));
+            assert!(bit(&n, 1));
+            assert!(!bit(&n, 2));
+            assert!(!bit(&n, 100));
+            assert_eq!(big(0b1110), set_bit(&n, 2, true));
+            assert_eq!(big(0b0010), set_bit(&n, 3, false));
+            assert_eq!(n, set_bit(&n, 3, true));
+        }
+    }
+
+    mod primes {
+        use super::*;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        #[test]
+        fn miller_rabin() {
+            let mut rng = StdRng::seed_from_u64(39);
+            let primes: Vec<u64> = (2..200)
+                .filter(|n| is_probable_prime(&big(*n), MILLER_RABIN_ROUNDS, &mut rng))
+                .collect();
+
+            assert_eq!(46, primes.len());
+            assert_eq!(199, *primes.last().unwrap());
+            assert!(!is_probable_prime(&big(0), 1, &mut rng));
+            assert!(!is_probable_prime(&big(1), 1, &mut rng));
+            // Carmichael numbers fool the Fermat test but not Miller-Rabin.
+            assert!(!is_probable_prime(&big(561), 10, &mut rng));
+            assert!(!is_probable_prime(&big(41041), 10, &mut rng));
+            assert!(is_probable_prime(&big(2_147_483_647), 10, &mut rng));
+            assert!(is_probable_prime(
+                &from_hex(b"7fffffffffffffffffffffffffffffff"),
+                10,
+                &mut rng
+            ));
+            assert!(!is_probable_prime(
+                &from_hex(b"ffffffffffffffffffffffffffffffff"),
+                10,
+                &mut rng
+            ));
+        }
+
+        #[test]
+        fn generated_primes_have_exact_size() {
+            let mut rng = StdRng::seed_from_u64(40);
+
+            for size in [16, 64, 128].iter() {
+                let prime = generate_prime(*size, &mut rng);
+
+                assert_eq!(*size, bits(&prime));
+                assert!(bit(&prime, size - 2));
+                assert!(is_probable_prime(&prime, MILLER_RABIN_ROUNDS, &mut rng));
+            }
+        }
+    }
+}
@@ -102,10 +102,61 @@ fn calculate_dimensions(block_size: usize, length: usize) -> (usize, usize) {
     (n, m)
 }
 
+// Always adds between 1 and `block_size` bytes, unlike `with_padding_from` which leaves
+// aligned input alone, so the padding can be removed unambiguously.
+pub fn pkcs7_pad(block_size: usize, data: &[u8]) -> Vec<u8> {
+    let padding_length = block_size - data.len() % block_size;
+
+    [data, &vec![padding_length as u8; padding_length]].concat()
+}
+
+pub fn pkcs7_unpad(block_size: usize, data: &[u8]) -> Option<Vec<u8>> {
+    let padding_length = *data.last()? as usize;
+    if padding_length == 0 || padding_length > block_size || padding_length > data.len() {
+        return None;
+    }
+
+    let (message, padding) = data.split_at(data.len() - padding_length);
+    if padding.iter().any(|&b| b as usize != padding_length) {
+        return None;
+    }
+
+    Some(message.to_vec())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    mod pkcs7 {
+        use super::*;
+
+        #[test]
+        fn round_trip() {
+            for length in 0..40 {
+                let data = vec![b'A'; length];
+                let padded = pkcs7_pad(16, &data);
+
+                assert_eq!(0, padded.len() % 16);
+                assert!(padded.len() > data.len());
+                assert_eq!(Some(data), pkcs7_unpad(16, &padded));
+            }
+        }
+
+        #[test]
+        fn rejects_invalid_padding() {
+            assert_eq!(None, pkcs7_unpad(16, b""));
+            assert_eq!(None, pkcs7_unpad(16, b"ICE ICE BABY\x00"));
+            assert_eq!(None, pkcs7_unpad(16, b"ICE ICE BABY\x05\x05\x05\x05"));
+            assert_eq!(None, pkcs7_unpad(16, b"ICE ICE BABY\x01\x02\x03\x04"));
+            assert_eq!(None, pkcs7_unpad(4, b"ICE ICE BABY\x05\x05\x05\x05\x05"));
+            assert_eq!(
+                Some(b"ICE ICE BABY".to_vec()),
+                pkcs7_unpad(16, b"ICE ICE BABY\x04\x04\x04\x04")
+            );
+        }
+    }
+
     mod chunk_slice {
         use super::*;
 
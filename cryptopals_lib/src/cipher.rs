@@ -2,6 +2,7 @@ extern crate openssl;
 
 use super::blocks::Blocks;
 use super::heuristics::contain_duplicates;
+use super::oracle::OracleError;
 use super::xor;
 use std::{collections::HashMap, fmt};
 
@@ -9,13 +10,15 @@ use std::{collections::HashMap, fmt};
 pub enum EncryptionMode {
     ECB,
     CBC,
+    CTR,
 }
 
 impl fmt::Display for EncryptionMode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            EncryptionMode::ECB => write!(f, "{}", "ECB"),
-            EncryptionMode::CBC => write!(f, "{}", "CBC"),
+            EncryptionMode::ECB => write!(f, "ECB"),
+            EncryptionMode::CBC => write!(f, "CBC"),
+            EncryptionMode::CTR => write!(f, "CTR"),
         }
     }
 }
@@ -36,6 +39,28 @@ pub fn ecb_mode_decrypt(data: &[u8], key: &[u8]) -> Vec<u8> {
         .expect("Failed to decrypt ecb mode decryption with specified key")
 }
 
+// Decrypts without checking padding, so single blocks can be decrypted under any key.
+fn ecb_block_decrypt(block: &[u8], key: &[u8]) -> Vec<u8> {
+    use openssl::symm::{Cipher, Crypter, Mode};
+    let cipher = Cipher::aes_128_ecb();
+
+    let mut crypter = Crypter::new(cipher, Mode::Decrypt, key, None)
+        .expect("Failed to create ecb mode decrypter with specified key");
+    crypter.pad(false);
+
+    let mut message = vec![0; block.len() + cipher.block_size()];
+    let count = crypter
+        .update(block, &mut message)
+        .expect("Failed to decrypt ecb mode block with specified key");
+    let count = count
+        + crypter
+            .finalize(&mut message[count..])
+            .expect("Failed to decrypt ecb mode block with specified key");
+    message.truncate(count);
+
+    message
+}
+
 pub fn detect_encryption_mode(data: &[u8]) -> EncryptionMode {
     let blocks = Blocks::from(16, data).chunk_slice();
 
@@ -78,15 +103,11 @@ pub fn cbc_mode_encrypt(data: &[u8], key: &[u8], iv: &[u8]) -> Vec<u8> {
 pub fn cbc_mode_decrypt(data: &[u8], key: &[u8], iv: &[u8]) -> Vec<u8> {
     let padded_data = Blocks::with_padding_from(16, data);
     let mut prev_block = iv.to_vec();
-    let ecb_mode_salt: Vec<u8> = vec![
-        96, 250, 54, 112, 126, 69, 244, 153, 219, 160, 242, 91, 146, 35, 1, 165,
-    ];
 
     padded_data
         .into_iter()
         .map(|block| {
-            let salty_block = [block.clone(), ecb_mode_salt.clone()].concat();
-            let decrypt_message = ecb_mode_decrypt(&salty_block, key);
+            let decrypt_message = ecb_block_decrypt(&block, key);
             let plaintext = xor::fixed_xor(&decrypt_message, &prev_block);
             prev_block = block;
 
@@ -96,6 +117,167 @@ pub fn cbc_mode_decrypt(data: &[u8], key: &[u8], iv: &[u8]) -> Vec<u8> {
         .collect()
 }
 
+// A keyed block cipher, so modes and MACs can be written once for any of them.
+pub trait BlockCipher {
+    const BLOCK_SIZE: usize;
+
+    fn new(key: &[u8]) -> Self;
+
+    fn encrypt_block(&self, block: &[u8]) -> Vec<u8>;
+
+    fn decrypt_block(&self, block: &[u8]) -> Vec<u8>;
+}
+
+pub struct Aes128 {
+    key: Vec<u8>,
+}
+
+impl BlockCipher for Aes128 {
+    const BLOCK_SIZE: usize = 16;
+
+    fn new(key: &[u8]) -> Self {
+        assert_eq!(16, key.len(), "AES-128 takes a 16 byte key");
+        Self { key: key.to_vec() }
+    }
+
+    fn encrypt_block(&self, block: &[u8]) -> Vec<u8> {
+        ecb_mode_encrypt(block, &self.key)[..Self::BLOCK_SIZE].to_vec()
+    }
+
+    fn decrypt_block(&self, block: &[u8]) -> Vec<u8> {
+        ecb_block_decrypt(block, &self.key)
+    }
+}
+
+// Plain CBC over whole blocks, padding is left to the caller.
+pub fn cbc_encrypt<C: BlockCipher>(cipher: &C, iv: &[u8], data: &[u8]) -> Vec<u8> {
+    assert!(
+        data.len().is_multiple_of(C::BLOCK_SIZE),
+        "CBC input must be whole blocks"
+    );
+    let mut prev_block = iv.to_vec();
+
+    data.chunks(C::BLOCK_SIZE)
+        .flat_map(|block| {
+            prev_block = cipher.encrypt_block(&xor::fixed_xor(block, &prev_block));
+            prev_block.clone()
+        })
+        .collect()
+}
+
+pub fn cbc_decrypt<C: BlockCipher>(cipher: &C, iv: &[u8], data: &[u8]) -> Vec<u8> {
+    assert!(
+        data.len().is_multiple_of(C::BLOCK_SIZE),
+        "CBC input must be whole blocks"
+    );
+    let mut prev_block = iv;
+
+    data.chunks(C::BLOCK_SIZE)
+        .flat_map(|block| {
+            let plaintext = xor::fixed_xor(&cipher.decrypt_block(block), prev_block);
+            prev_block = block;
+
+            plaintext
+        })
+        .collect()
+}
+
+pub fn ctr_mode_encrypt(data: &[u8], key: &[u8], nonce: u64) -> Vec<u8> {
+    let keystream = ctr_keystream(key, nonce, 0, data.len());
+    xor::fixed_xor(data, &keystream)
+}
+
+pub fn ctr_mode_decrypt(data: &[u8], key: &[u8], nonce: u64) -> Vec<u8> {
+    ctr_mode_encrypt(data, key, nonce)
+}
+
+pub fn ctr_mode_edit(
+    ciphertext: &[u8],
+    key: &[u8],
+    nonce: u64,
+    offset: usize,
+    newtext: &[u8],
+) -> Vec<u8> {
+    let end = offset + newtext.len();
+    if end > ciphertext.len() {
+        panic!("Edit runs past the end of the ciphertext.");
+    }
+
+    let keystream = ctr_keystream(key, nonce, offset, newtext.len());
+    let edited = xor::fixed_xor(newtext, &keystream);
+
+    [&ciphertext[..offset], &edited, &ciphertext[end..]].concat()
+}
+
+// Only generates the keystream blocks covering `offset..offset + len`, so edits deep into a
+// large ciphertext don't pay for everything before them.
+fn ctr_keystream(key: &[u8], nonce: u64, offset: usize, len: usize) -> Vec<u8> {
+    if len == 0 {
+        return vec![];
+    }
+
+    let first_block = offset / 16;
+    let last_block = (offset + len - 1) / 16;
+
+    let counter_blocks: Vec<u8> = (first_block..=last_block)
+        .flat_map(|counter| {
+            [nonce.to_le_bytes(), (counter as u64).to_le_bytes()]
+                .concat()
+                .into_iter()
+        })
+        .collect();
+
+    let keystream = ecb_mode_encrypt(&counter_blocks, key);
+    let skip = offset % 16;
+
+    keystream[skip..skip + len].to_vec()
+}
+
+pub fn break_random_access_ctr<F>(ciphertext: &[u8], edit: F) -> Vec<u8>
+where
+    F: Fn(&[u8], usize, &[u8]) -> Vec<u8>,
+{
+    // Writing the ciphertext back over itself XORs it with the keystream a second time.
+    edit(ciphertext, 0, ciphertext)
+}
+
+pub fn ctr_bitflip<F>(encrypt: F, offset: usize, payload: &[u8]) -> Vec<u8>
+where
+    F: Fn(&[u8]) -> Vec<u8>,
+{
+    let filler = vec![b'A'; payload.len()];
+    let mut secret = encrypt(&filler);
+
+    secret[offset..offset + payload.len()]
+        .iter_mut()
+        .zip(xor::fixed_xor(&filler, payload))
+        .for_each(|(byte, flip)| *byte ^= flip);
+
+    secret
+}
+
+pub fn recover_key_from_iv<E, D>(encrypt: E, decrypt: D) -> Option<Vec<u8>>
+where
+    E: Fn(&[u8]) -> Vec<u8>,
+    D: Fn(&[u8]) -> Result<Vec<u8>, OracleError>,
+{
+    // The attack needs at least three blocks of ciphertext to rearrange.
+    let secret = encrypt(&[]);
+    if secret.len() < 48 {
+        return None;
+    }
+
+    let first_block = &secret[..16];
+    let modified = [first_block, &[0; 16], first_block, &secret[48..]].concat();
+
+    match decrypt(&modified) {
+        Err(OracleError::HighAscii(plaintext)) => {
+            Some(xor::fixed_xor(&plaintext[..16], &plaintext[32..48]))
+        }
+        _ => None,
+    }
+}
+
 pub fn aes_128_ecb_decrypt<F>(oracle: F, data: &[u8]) -> Vec<u8>
 where
     F: Fn(&[u8]) -> Vec<u8>,
@@ -130,6 +312,58 @@ where
     known_bytes
 }
 
+// Guesses what follows `prefix` one byte at a time: the guess that compresses into the
+// shortest ciphertext extends the match against the secret. Lengths only move in whole bytes
+// for a stream cipher and whole blocks for CBC, so a few bytes of incompressible filler are
+// probed in front until exactly one candidate comes out shorter than the rest. A correct
+// guess barely changes the compressed length, so probing starts from the filler that worked
+// last time. Stops when `terminator` wins.
+pub fn compression_attack<F>(
+    oracle: F,
+    prefix: &[u8],
+    alphabet: &[u8],
+    terminator: u8,
+) -> Option<Vec<u8>>
+where
+    F: Fn(&[u8]) -> usize,
+{
+    let filler: Vec<u8> = (0x80..0xa0).collect();
+    let candidates: Vec<u8> = alphabet
+        .iter()
+        .copied()
+        .chain(std::iter::once(terminator))
+        .collect();
+    let mut known = prefix.to_vec();
+    let mut last_pad = 0;
+
+    loop {
+        let (pad, guess) = (last_pad..=filler.len())
+            .chain(0..last_pad)
+            .find_map(|pad| {
+                let lengths: Vec<usize> = candidates
+                    .iter()
+                    .map(|&candidate| oracle(&[&filler[..pad], &known, &[candidate]].concat()))
+                    .collect();
+                let shortest = *lengths.iter().min()?;
+
+                let mut winners = candidates
+                    .iter()
+                    .zip(&lengths)
+                    .filter(|(_, &length)| length == shortest);
+                match (winners.next(), winners.next()) {
+                    (Some((&candidate, _)), None) => Some((pad, candidate)),
+                    _ => None,
+                }
+            })?;
+        last_pad = pad;
+
+        if guess == terminator {
+            return Some(known[prefix.len()..].to_vec());
+        }
+        known.push(guess);
+    }
+}
+
 fn find_block_size<F>(oracle: F) -> usize
 where
     F: Fn(&[u8]) -> Vec<u8>,
@@ -222,6 +456,124 @@ mod test {
             let message = cbc_mode_decrypt(&secret, key, &iv);
             assert_eq!(data.to_vec(), message)
         }
+
+        #[test]
+        fn encrypt_decrypt_with_any_key() {
+            let data = b"Figuring to decrypt cbc mode encryption with any key!";
+            let key = b"ORANGE SUBMARINE";
+            let iv = vec![b'0'; 16];
+
+            let secret = cbc_mode_encrypt(data, key, &iv);
+            let message = cbc_mode_decrypt(&secret, key, &iv);
+            assert_eq!(data.to_vec(), message[..data.len()].to_vec())
+        }
+
+        #[test]
+        fn generic_cbc_matches_openssl() {
+            use openssl::symm::{encrypt, Cipher};
+
+            let data = b"Figuring to decrypt ecb mode encryption with key and back again!";
+            let key = b"YELLOW SUBMARINE";
+            let iv = b"0123456789abcdef";
+            let cipher = Aes128::new(key);
+
+            let expected = encrypt(Cipher::aes_128_cbc(), key, Some(iv), data).unwrap();
+            let secret = cbc_encrypt(&cipher, iv, data);
+            assert_eq!(expected[..data.len()].to_vec(), secret);
+            assert_eq!(data.to_vec(), cbc_decrypt(&cipher, iv, &secret));
+        }
+
+        #[test]
+        fn _recover_key_from_iv() {
+            use crate::oracle::cbc_key_as_iv_oracle_generator;
+
+            let (encrypt, decrypt) = cbc_key_as_iv_oracle_generator();
+            let secret = encrypt(b"hello");
+            let key = recover_key_from_iv(&encrypt, &decrypt).unwrap();
+
+            let message = cbc_mode_decrypt(&secret, &key, &key);
+            assert!(message.starts_with(b"comment1=cooking%20MCs;userdata=hello;"))
+        }
+
+        #[test]
+        fn recover_key_from_short_ciphertext() {
+            let encrypt = |_: &[u8]| vec![0; 32];
+            let decrypt = |_: &[u8]| -> Result<Vec<u8>, OracleError> { Ok(vec![]) };
+
+            assert_eq!(None, recover_key_from_iv(encrypt, decrypt));
+        }
+    }
+
+    mod ctr {
+        use super::*;
+
+        #[test]
+        fn decrypt_known_ciphertext() {
+            let data = crate::base64::decode(
+                b"L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ==",
+            );
+            let key = b"YELLOW SUBMARINE";
+
+            let message = ctr_mode_decrypt(&data, key, 0);
+            assert_eq!(
+                b"Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby ".to_vec(),
+                message
+            )
+        }
+
+        #[test]
+        fn encrypt_decrypt() {
+            let data = b"Figuring to decrypt ctr mode encryption with key and back again";
+            let key = b"YELLOW SUBMARINE";
+
+            let secret = ctr_mode_encrypt(data, key, 42);
+            let message = ctr_mode_decrypt(&secret, key, 42);
+            assert_eq!(data.to_vec(), message)
+        }
+
+        #[test]
+        fn edit() {
+            let data = b"Figuring to decrypt ctr mode encryption with key and back again";
+            let key = b"YELLOW SUBMARINE";
+            let secret = ctr_mode_encrypt(data, key, 7);
+
+            let edited = ctr_mode_edit(&secret, key, 7, 20, b"CTR");
+            let message = ctr_mode_decrypt(&edited, key, 7);
+
+            assert_eq!(secret.len(), edited.len());
+            assert_eq!(
+                b"Figuring to decrypt CTR mode encryption with key and back again".to_vec(),
+                message
+            )
+        }
+
+        #[test]
+        fn _break_random_access_ctr() {
+            use crate::oracle::ctr_edit_oracle_generator;
+
+            let data =
+                b"Figuring to decrypt ctr mode encryption with key and back again!".repeat(64);
+
+            let (secret, edit) = ctr_edit_oracle_generator(&data);
+            let message = break_random_access_ctr(&secret, edit);
+
+            assert_eq!(data, message)
+        }
+    }
+
+    mod bitflip {
+        use super::*;
+
+        #[test]
+        fn _ctr_bitflip() {
+            use crate::oracle::{ctr_comment_oracle_generator, COMMENT_PREFIX};
+
+            let (encrypt, check_admin) = ctr_comment_oracle_generator();
+            assert!(!check_admin(&encrypt(b";admin=true;")));
+
+            let secret = ctr_bitflip(&encrypt, COMMENT_PREFIX.len(), b";admin=true;");
+            assert!(check_admin(&secret))
+        }
     }
 
     mod detection {
@@ -246,4 +598,33 @@ mod test {
             assert_eq!(EncryptionMode::CBC, detect_encryption_mode(&secret))
         }
     }
+
+    mod compression {
+        use super::*;
+        use crate::deflate::compress;
+
+        const SECRET: &[u8] = b"GET /?token=s3cr3tT0k3n HTTP/1.1\n";
+        const ALPHABET: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+        #[test]
+        fn recovers_through_byte_lengths() {
+            let oracle = |payload: &[u8]| compress(&[SECRET, payload].concat()).len();
+
+            assert_eq!(
+                Some(b"s3cr3tT0k3n".to_vec()),
+                compression_attack(oracle, b"token=", ALPHABET, b' ')
+            );
+        }
+
+        #[test]
+        fn recovers_through_block_lengths() {
+            let oracle =
+                |payload: &[u8]| compress(&[SECRET, payload].concat()).len() / 16 * 16 + 16;
+
+            assert_eq!(
+                Some(b"s3cr3tT0k3n".to_vec()),
+                compression_attack(oracle, b"token=", ALPHABET, b' ')
+            );
+        }
+    }
 }
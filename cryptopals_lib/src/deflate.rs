@@ -0,0 +1,345 @@
+// A small DEFLATE (RFC 1951) compressor: greedy LZ77 over a 32K window, written out as a
+// single block with the fixed Huffman codes. It won't win any benchmarks, but every repeat
+// of three or more bytes turns into a back-reference, which is all a compression oracle
+// needs.
+use std::collections::HashMap;
+
+const WINDOW_SIZE: usize = 32 * 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 1024;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+const END_OF_BLOCK: u16 = 256;
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Literal(u8),
+    Match { length: usize, distance: usize },
+}
+
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+
+    // BFINAL, then BTYPE 01 for fixed Huffman codes.
+    writer.write_bits(1, 1);
+    writer.write_bits(1, 2);
+
+    for token in lz77(data) {
+        match token {
+            Token::Literal(byte) => write_literal(&mut writer, u16::from(byte)),
+            Token::Match { length, distance } => {
+                let code = LENGTH_BASE
+                    .iter()
+                    .rposition(|&base| base as usize <= length)
+                    .unwrap();
+                write_literal(&mut writer, 257 + code as u16);
+                writer.write_bits(
+                    (length - LENGTH_BASE[code] as usize) as u32,
+                    LENGTH_EXTRA[code],
+                );
+
+                let code = DISTANCE_BASE
+                    .iter()
+                    .rposition(|&base| base as usize <= distance)
+                    .unwrap();
+                writer.write_code(code as u32, 5);
+                writer.write_bits(
+                    (distance - DISTANCE_BASE[code] as usize) as u32,
+                    DISTANCE_EXTRA[code],
+                );
+            }
+        }
+    }
+    write_literal(&mut writer, END_OF_BLOCK);
+
+    writer.finish()
+}
+
+// Takes the longest match at each position, preferring the closest on ties, and never looks
+// ahead for a better one.
+fn lz77(data: &[u8]) -> Vec<Token> {
+    let mut chains: HashMap<&[u8], Vec<usize>> = HashMap::new();
+    let mut tokens = vec![];
+    let mut position = 0;
+
+    while position < data.len() {
+        let (length, distance) = longest_match(data, position, &chains);
+
+        let advance = if length >= MIN_MATCH {
+            tokens.push(Token::Match { length, distance });
+            length
+        } else {
+            tokens.push(Token::Literal(data[position]));
+            1
+        };
+
+        for start in position..position + advance {
+            if start + MIN_MATCH <= data.len() {
+                chains
+                    .entry(&data[start..start + MIN_MATCH])
+                    .or_default()
+                    .push(start);
+            }
+        }
+        position += advance;
+    }
+
+    tokens
+}
+
+fn longest_match(
+    data: &[u8],
+    position: usize,
+    chains: &HashMap<&[u8], Vec<usize>>,
+) -> (usize, usize) {
+    if position + MIN_MATCH > data.len() {
+        return (0, 0);
+    }
+    let candidates = match chains.get(&data[position..position + MIN_MATCH]) {
+        Some(candidates) => candidates,
+        None => return (0, 0),
+    };
+
+    let limit = (data.len() - position).min(MAX_MATCH);
+    let mut best = (0, 0);
+    for &start in candidates.iter().rev().take(MAX_CHAIN) {
+        if position - start > WINDOW_SIZE {
+            break;
+        }
+        let length = (0..limit)
+            .take_while(|&i| data[start + i] == data[position + i])
+            .count();
+        if length > best.0 {
+            best = (length, position - start);
+        }
+        if length == limit {
+            break;
+        }
+    }
+
+    best
+}
+
+// The fixed literal/length code from section 3.2.6 of the RFC.
+fn write_literal(writer: &mut BitWriter, symbol: u16) {
+    let symbol = u32::from(symbol);
+    match symbol {
+        0..=143 => writer.write_code(0x30 + symbol, 8),
+        144..=255 => writer.write_code(0x190 + symbol - 144, 9),
+        256..=279 => writer.write_code(symbol - 256, 7),
+        _ => writer.write_code(0xc0 + symbol - 280, 8),
+    }
+}
+
+// DEFLATE packs bits from the least significant end of each byte. Extra bits go in as they
+// are, while Huffman codes are sent most significant bit first.
+struct BitWriter {
+    bytes: Vec<u8>,
+    bit_count: usize,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self {
+            bytes: vec![],
+            bit_count: 0,
+        }
+    }
+
+    fn write_bits(&mut self, value: u32, count: u8) {
+        for i in 0..count {
+            if self.bit_count.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            let bit = ((value >> i) & 1) as u8;
+            *self.bytes.last_mut().unwrap() |= bit << (self.bit_count % 8);
+            self.bit_count += 1;
+        }
+    }
+
+    fn write_code(&mut self, code: u32, length: u8) {
+        let reversed = code.reverse_bits() >> (32 - u32::from(length));
+        self.write_bits(reversed, length);
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hex;
+
+    struct BitReader<'a> {
+        bytes: &'a [u8],
+        position: usize,
+    }
+
+    impl BitReader<'_> {
+        fn bit(&mut self) -> u32 {
+            let bit = (self.bytes[self.position / 8] >> (self.position % 8)) & 1;
+            self.position += 1;
+            u32::from(bit)
+        }
+
+        // Extra bits, least significant first.
+        fn bits(&mut self, count: u8) -> u32 {
+            (0..count).fold(0, |value, i| value | (self.bit() << i))
+        }
+
+        // Huffman codes, most significant first.
+        fn code(&mut self, code: u32, count: u8) -> u32 {
+            (0..count).fold(code, |code, _| (code << 1) | self.bit())
+        }
+    }
+
+    // Just enough of a decoder to read back what `compress` writes: one final block with the
+    // fixed codes.
+    fn inflate(data: &[u8]) -> Vec<u8> {
+        let mut reader = BitReader {
+            bytes: data,
+            position: 0,
+        };
+        assert_eq!(
+            0b11,
+            reader.bits(3),
+            "Expected one final fixed Huffman block"
+        );
+
+        let mut plaintext = vec![];
+        loop {
+            let symbol = match reader.code(0, 7) {
+                code @ 0..=0x17 => code + 256,
+                code => match reader.code(code, 1) {
+                    code @ 0x30..=0xbf => code - 0x30,
+                    code @ 0xc0..=0xc7 => code - 0xc0 + 280,
+                    code => reader.code(code, 1) - 0x190 + 144,
+                },
+            };
+
+            match symbol {
+                0..=255 => plaintext.push(symbol as u8),
+                256 => return plaintext,
+                _ => {
+                    let code = symbol as usize - 257;
+                    let length =
+                        LENGTH_BASE[code] as usize + reader.bits(LENGTH_EXTRA[code]) as usize;
+                    let code = reader.code(0, 5) as usize;
+                    let distance =
+                        DISTANCE_BASE[code] as usize + reader.bits(DISTANCE_EXTRA[code]) as usize;
+
+                    for _ in 0..length {
+                        plaintext.push(plaintext[plaintext.len() - distance]);
+                    }
+                }
+            }
+        }
+    }
+
+    mod tokens {
+        use super::*;
+
+        #[test]
+        fn repeats_become_matches() {
+            assert_eq!(
+                vec![
+                    Token::Literal(b'a'),
+                    Token::Literal(b'b'),
+                    Token::Literal(b'c'),
+                    Token::Match {
+                        length: 6,
+                        distance: 3
+                    },
+                    Token::Literal(b'd'),
+                ],
+                lz77(b"abcabcabcd")
+            );
+        }
+
+        #[test]
+        fn short_repeats_stay_literal() {
+            assert!(lz77(b"abxab")
+                .iter()
+                .all(|token| matches!(token, Token::Literal(_))));
+        }
+
+        #[test]
+        fn prefers_longest_match() {
+            let tokens = lz77(b"sessionid=abcd\nsessionid=xyz&sessionid=abcd");
+            assert_eq!(
+                Some(&Token::Match {
+                    length: 14,
+                    distance: 29
+                }),
+                tokens.last()
+            );
+        }
+    }
+
+    mod compression {
+        use super::*;
+
+        #[test]
+        fn round_trips_through_inflate() {
+            let inputs: Vec<Vec<u8>> = vec![
+                vec![],
+                b"a".to_vec(),
+                b"Cookie: sessionid=TmV2ZXIgcmV2ZWFsIHRoZSBXdS1UYW5nIFNlY3JldCE=".to_vec(),
+                vec![0; 1000],
+                (0..=255).cycle().take(70_000).collect(),
+                (0..5000u32)
+                    .flat_map(|i| (i * i % 251).to_be_bytes())
+                    .collect(),
+            ];
+
+            for input in inputs {
+                assert_eq!(input, inflate(&compress(&input)));
+            }
+        }
+
+        // Checked against zlib's raw inflate.
+        #[test]
+        fn known_answers() {
+            let vectors: [(&[u8], &[u8]); 4] = [
+                (b"", b"0300"),
+                (b"a", b"4b0400"),
+                (b"abcabcabcd", b"4b4c4a86a01400"),
+                (
+                    b"Wu-Tang Clan ain't nuthing ta f' wit, Wu-Tang Clan ain't nuthing ta f' wit",
+                    b"0b2fd50d49cc4b5770ce49cc5348cccc532f51c82b2dc9c8040a95242aa4a92b946796e82810a30a00",
+                ),
+            ];
+
+            for (input, expected) in vectors.iter() {
+                assert_eq!(hex::decode(expected), compress(input));
+            }
+        }
+
+        #[test]
+        fn repeats_shrink() {
+            let secret = b"sessionid=TmV2ZXIgcmV2ZWFsIHRoZSBXdS1UYW5nIFNlY3JldCE=";
+            let right = compress(&[&secret[..], b"sessionid=TmV2"].concat());
+            let wrong = compress(&[&secret[..], b"sessionid=XyZw"].concat());
+
+            assert!(right.len() < wrong.len());
+            assert!(compress(&[0; 1000]).len() < 20);
+        }
+    }
+}
@@ -0,0 +1,788 @@
+use super::bignum::{self, BigUint, RandBigInt};
+use super::blocks;
+use super::cipher;
+use super::hash::Hash;
+use super::network::{Action, Direction, Interceptor, Party};
+use super::sha1::Sha1;
+use super::sha256::Sha256;
+use rand::Rng;
+use std::error::Error;
+use std::fmt;
+
+// The prime from challenge 33, which is also the RFC 3526 1536-bit MODP group.
+const NIST_PRIME: &str = concat!(
+    "ffffffffffffffffc90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74020bbea63b139b22514a08798e3404dd",
+    "ef9519b3cd3a431b302b0a6df25f14374fe1356d6d51c245e485b576625e7ec6f44c42e9a637ed6b0bff5cb6f406b7ed",
+    "ee386bfb5a899fa5ae9f24117c4b1fe649286651ece45b3dc2007cb8a163bf0598da48361c55d39a69163fa8fd24cf5f",
+    "83655d23dca3ad961c62f356208552bb9ed529077096966d670c354e4abc9804f1746c08ca237327ffffffffffffffff",
+);
+
+const MODP_2048: &str = concat!(
+    "ffffffffffffffffc90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74020bbea63b139b22514a08798e3404dd",
+    "ef9519b3cd3a431b302b0a6df25f14374fe1356d6d51c245e485b576625e7ec6f44c42e9a637ed6b0bff5cb6f406b7ed",
+    "ee386bfb5a899fa5ae9f24117c4b1fe649286651ece45b3dc2007cb8a163bf0598da48361c55d39a69163fa8fd24cf5f",
+    "83655d23dca3ad961c62f356208552bb9ed529077096966d670c354e4abc9804f1746c08ca18217c32905e462e36ce3b",
+    "e39e772c180e86039b2783a2ec07a28fb5c55df06f4c52c9de2bcbf6955817183995497cea956ae515d2261898fa0510",
+    "15728e5a8aacaa68ffffffffffffffff",
+);
+
+const MODP_3072: &str = concat!(
+    "ffffffffffffffffc90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74020bbea63b139b22514a08798e3404dd",
+    "ef9519b3cd3a431b302b0a6df25f14374fe1356d6d51c245e485b576625e7ec6f44c42e9a637ed6b0bff5cb6f406b7ed",
+    "ee386bfb5a899fa5ae9f24117c4b1fe649286651ece45b3dc2007cb8a163bf0598da48361c55d39a69163fa8fd24cf5f",
+    "83655d23dca3ad961c62f356208552bb9ed529077096966d670c354e4abc9804f1746c08ca18217c32905e462e36ce3b",
+    "e39e772c180e86039b2783a2ec07a28fb5c55df06f4c52c9de2bcbf6955817183995497cea956ae515d2261898fa0510",
+    "15728e5a8aaac42dad33170d04507a33a85521abdf1cba64ecfb850458dbef0a8aea71575d060c7db3970f85a6e1e4c7",
+    "abf5ae8cdb0933d71e8c94e04a25619dcee3d2261ad2ee6bf12ffa06d98a0864d87602733ec86a64521f2b18177b200c",
+    "bbe117577a615d6c770988c0bad946e208e24fa074e5ab3143db5bfce0fd108e4b82d120a93ad2caffffffffffffffff",
+);
+
+const FFDHE_2048: &str = concat!(
+    "ffffffffffffffffadf85458a2bb4a9aafdc5620273d3cf1d8b9c583ce2d3695a9e13641146433fbcc939dce249b3ef9",
+    "7d2fe363630c75d8f681b202aec4617ad3df1ed5d5fd65612433f51f5f066ed0856365553ded1af3b557135e7f57c935",
+    "984f0c70e0e68b77e2a689daf3efe8721df158a136ade73530acca4f483a797abc0ab182b324fb61d108a94bb2c8e3fb",
+    "b96adab760d7f4681d4f42a3de394df4ae56ede76372bb190b07a7c8ee0a6d709e02fce1cdf7e2ecc03404cd28342f61",
+    "9172fe9ce98583ff8e4f1232eef28183c3fe3b1b4c6fad733bb5fcbc2ec22005c58ef1837d1683b2c6f34a26c1b2effa",
+    "886b423861285c97ffffffffffffffff",
+);
+
+const FFDHE_3072: &str = concat!(
+    "ffffffffffffffffadf85458a2bb4a9aafdc5620273d3cf1d8b9c583ce2d3695a9e13641146433fbcc939dce249b3ef9",
+    "7d2fe363630c75d8f681b202aec4617ad3df1ed5d5fd65612433f51f5f066ed0856365553ded1af3b557135e7f57c935",
+    "984f0c70e0e68b77e2a689daf3efe8721df158a136ade73530acca4f483a797abc0ab182b324fb61d108a94bb2c8e3fb",
+    "b96adab760d7f4681d4f42a3de394df4ae56ede76372bb190b07a7c8ee0a6d709e02fce1cdf7e2ecc03404cd28342f61",
+    "9172fe9ce98583ff8e4f1232eef28183c3fe3b1b4c6fad733bb5fcbc2ec22005c58ef1837d1683b2c6f34a26c1b2effa",
+    "886b4238611fcfdcde355b3b6519035bbc34f4def99c023861b46fc9d6e6c9077ad91d2691f7f7ee598cb0fac186d91c",
+    "aefe130985139270b4130c93bc437944f4fd4452e2d74dd364f2e21e71f54bff5cae82ab9c9df69ee86d2bc522363a0d",
+    "abc521979b0deada1dbf9a42d5c4484e0abcd06bfa53ddef3c1b20ee3fd59d7c25e41d2b66c62e37ffffffffffffffff",
+);
+
+pub const AES_KEY_SIZE: usize = 16;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Group {
+    pub p: BigUint,
+    pub g: BigUint,
+    // Order of the subgroup generated by `g`, when known.
+    pub q: Option<BigUint>,
+}
+
+impl Group {
+    pub fn new(p: BigUint, g: BigUint, q: Option<BigUint>) -> Self {
+        Self { p, g, q }
+    }
+
+    // A safe prime group where g = 2 generates the subgroup of order (p - 1) / 2.
+    fn safe_prime(p: &str) -> Self {
+        let p = bignum::from_hex(p.as_bytes());
+        let q = (&p - 1u32) >> 1;
+
+        Self::new(p, BigUint::from(2u32), Some(q))
+    }
+
+    pub fn nist() -> Self {
+        Self::safe_prime(NIST_PRIME)
+    }
+
+    pub fn modp_1536() -> Self {
+        Self::nist()
+    }
+
+    pub fn modp_2048() -> Self {
+        Self::safe_prime(MODP_2048)
+    }
+
+    pub fn modp_3072() -> Self {
+        Self::safe_prime(MODP_3072)
+    }
+
+    pub fn ffdhe_2048() -> Self {
+        Self::safe_prime(FFDHE_2048)
+    }
+
+    pub fn ffdhe_3072() -> Self {
+        Self::safe_prime(FFDHE_3072)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum DhError {
+    OutOfRange,
+    NotInSubgroup,
+}
+
+impl fmt::Display for DhError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DhError::OutOfRange => write!(f, "Public key is outside of [2, p - 2]"),
+            DhError::NotInSubgroup => write!(f, "Public key is not in the prime order subgroup"),
+        }
+    }
+}
+
+impl Error for DhError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyPair {
+    pub private: BigUint,
+    pub public: BigUint,
+}
+
+impl KeyPair {
+    pub fn generate<R: Rng>(group: &Group, rng: &mut R) -> Self {
+        let private = generate_private_key(group, rng);
+        let public = public_key(group, &private);
+
+        Self { private, public }
+    }
+}
+
+// Draws from [1, q) when the subgroup order is known, otherwise from [1, p - 1).
+pub fn generate_private_key<R: Rng>(group: &Group, rng: &mut R) -> BigUint {
+    let upper = match &group.q {
+        Some(q) => q.clone(),
+        None => &group.p - 1u32,
+    };
+
+    rng.gen_biguint_range(&BigUint::from(1u32), &upper)
+}
+
+pub fn public_key(group: &Group, private: &BigUint) -> BigUint {
+    bignum::modpow(&group.g, private, &group.p)
+}
+
+// Rejects 0, 1 and p - 1 (and anything not reduced mod p), which would pin the shared
+// secret to a handful of values. The subgroup check costs a full exponentiation, so it's
+// opt-in.
+pub fn validate_public_key(
+    group: &Group,
+    public: &BigUint,
+    check_subgroup: bool,
+) -> Result<(), DhError> {
+    let two = BigUint::from(2u32);
+    if public < &two || public > &(&group.p - &two) {
+        return Err(DhError::OutOfRange);
+    }
+
+    if let (true, Some(q)) = (check_subgroup, &group.q) {
+        if bignum::modpow(public, q, &group.p) != BigUint::from(1u32) {
+            return Err(DhError::NotInSubgroup);
+        }
+    }
+
+    Ok(())
+}
+
+// The raw computation, trusting whatever the other side sent.
+pub fn shared_secret_unchecked(group: &Group, private: &BigUint, public: &BigUint) -> BigUint {
+    bignum::modpow(public, private, &group.p)
+}
+
+pub fn shared_secret(
+    group: &Group,
+    private: &BigUint,
+    public: &BigUint,
+) -> Result<BigUint, DhError> {
+    validate_public_key(group, public, true)?;
+
+    Ok(shared_secret_unchecked(group, private, public))
+}
+
+// Hashes the big endian bytes of the secret and keeps the first 16 bytes as an AES-128 key.
+pub fn derive_key<H: Hash>(secret: &BigUint) -> Vec<u8> {
+    let digest = H::digest(&bignum::to_bytes_be(secret));
+    if digest.len() < AES_KEY_SIZE {
+        panic!("Digest is too short for a {} byte key.", AES_KEY_SIZE);
+    }
+
+    digest[..AES_KEY_SIZE].to_vec()
+}
+
+pub fn derive_key_sha1(secret: &BigUint) -> Vec<u8> {
+    derive_key::<Sha1>(secret)
+}
+
+pub fn derive_key_sha256(secret: &BigUint) -> Vec<u8> {
+    derive_key::<Sha256>(secret)
+}
+
+// Challenges 34 and 35: Alice sends a message to Bob under a DH derived AES-CBC key and Bob
+// echoes it back under a fresh IV.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EchoMessage {
+    // Group parameters and the client's public key in one go, as in challenge 34.
+    Hello {
+        p: BigUint,
+        g: BigUint,
+        public: BigUint,
+    },
+    // Group negotiation ahead of the key exchange, as in challenge 35.
+    Negotiate {
+        p: BigUint,
+        g: BigUint,
+    },
+    Ack,
+    PublicKey(BigUint),
+    Encrypted {
+        ciphertext: Vec<u8>,
+        iv: Vec<u8>,
+    },
+}
+
+fn encrypt_message<R: Rng>(rng: &mut R, key: &[u8], message: &[u8]) -> EchoMessage {
+    let iv: Vec<u8> = (0..16).map(|_| rng.gen::<u8>()).collect();
+    let ciphertext = cipher::cbc_mode_encrypt(&blocks::pkcs7_pad(16, message), key, &iv);
+
+    EchoMessage::Encrypted { ciphertext, iv }
+}
+
+fn decrypt_message(key: &[u8], ciphertext: &[u8], iv: &[u8]) -> Option<Vec<u8>> {
+    blocks::pkcs7_unpad(16, &cipher::cbc_mode_decrypt(ciphertext, key, iv))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ClientState {
+    Start,
+    AwaitingAck,
+    AwaitingKey,
+    AwaitingEcho,
+    Done,
+}
+
+pub struct EchoClient<R: Rng> {
+    rng: R,
+    group: Group,
+    negotiate: bool,
+    key_pair: KeyPair,
+    message: Vec<u8>,
+    key: Option<Vec<u8>>,
+    state: ClientState,
+    pub echo: Option<Vec<u8>>,
+}
+
+impl<R: Rng> EchoClient<R> {
+    pub fn new(group: Group, message: &[u8], mut rng: R) -> Self {
+        let key_pair = KeyPair::generate(&group, &mut rng);
+
+        Self {
+            rng,
+            group,
+            negotiate: false,
+            key_pair,
+            message: message.to_vec(),
+            key: None,
+            state: ClientState::Start,
+            echo: None,
+        }
+    }
+
+    // Negotiates the group and waits for an ACK before sending the public key.
+    pub fn negotiating(mut self) -> Self {
+        self.negotiate = true;
+        self
+    }
+}
+
+impl<R: Rng> Party<EchoMessage> for EchoClient<R> {
+    fn start(&mut self) -> Vec<EchoMessage> {
+        let (p, g) = (self.group.p.clone(), self.group.g.clone());
+
+        if self.negotiate {
+            self.state = ClientState::AwaitingAck;
+            vec![EchoMessage::Negotiate { p, g }]
+        } else {
+            self.state = ClientState::AwaitingKey;
+            let public = self.key_pair.public.clone();
+            vec![EchoMessage::Hello { p, g, public }]
+        }
+    }
+
+    fn receive(&mut self, message: EchoMessage) -> Vec<EchoMessage> {
+        match (self.state, message) {
+            (ClientState::AwaitingAck, EchoMessage::Ack) => {
+                self.state = ClientState::AwaitingKey;
+                vec![EchoMessage::PublicKey(self.key_pair.public.clone())]
+            }
+            (ClientState::AwaitingKey, EchoMessage::PublicKey(public)) => {
+                let secret = shared_secret_unchecked(&self.group, &self.key_pair.private, &public);
+                let key = derive_key_sha1(&secret);
+                let reply = encrypt_message(&mut self.rng, &key, &self.message);
+
+                self.key = Some(key);
+                self.state = ClientState::AwaitingEcho;
+                vec![reply]
+            }
+            (ClientState::AwaitingEcho, EchoMessage::Encrypted { ciphertext, iv }) => {
+                let key = self.key.as_ref().expect("Key is set before echoing");
+                self.echo = decrypt_message(key, &ciphertext, &iv);
+                self.state = ClientState::Done;
+                vec![]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+pub struct EchoServer<R: Rng> {
+    rng: R,
+    validate: bool,
+    group: Option<Group>,
+    key: Option<Vec<u8>>,
+    pub received: Vec<Vec<u8>>,
+    pub error: Option<DhError>,
+}
+
+impl<R: Rng> EchoServer<R> {
+    pub fn new(rng: R) -> Self {
+        Self {
+            rng,
+            validate: false,
+            group: None,
+            key: None,
+            received: Vec::new(),
+            error: None,
+        }
+    }
+
+    // Range checks the client's public key and hangs up on a bad one.
+    pub fn validating(mut self) -> Self {
+        self.validate = true;
+        self
+    }
+
+    fn exchange(&mut self, group: Group, public: &BigUint) -> Vec<EchoMessage> {
+        if self.validate {
+            if let Err(error) = validate_public_key(&group, public, false) {
+                self.error = Some(error);
+                return vec![];
+            }
+        }
+
+        let key_pair = KeyPair::generate(&group, &mut self.rng);
+        let secret = shared_secret_unchecked(&group, &key_pair.private, public);
+        self.key = Some(derive_key_sha1(&secret));
+        self.group = Some(group);
+
+        vec![EchoMessage::PublicKey(key_pair.public)]
+    }
+}
+
+impl<R: Rng> Party<EchoMessage> for EchoServer<R> {
+    fn receive(&mut self, message: EchoMessage) -> Vec<EchoMessage> {
+        match message {
+            EchoMessage::Hello { p, g, public } => self.exchange(Group::new(p, g, None), &public),
+            EchoMessage::Negotiate { p, g } => {
+                self.group = Some(Group::new(p, g, None));
+                vec![EchoMessage::Ack]
+            }
+            EchoMessage::PublicKey(public) => match self.group.take() {
+                Some(group) => self.exchange(group, &public),
+                None => vec![],
+            },
+            EchoMessage::Encrypted { ciphertext, iv } => {
+                let key = match &self.key {
+                    Some(key) => key.clone(),
+                    None => return vec![],
+                };
+
+                match decrypt_message(&key, &ciphertext, &iv) {
+                    Some(message) => {
+                        let reply = encrypt_message(&mut self.rng, &key, &message);
+                        self.received.push(message);
+                        vec![reply]
+                    }
+                    None => vec![],
+                }
+            }
+            EchoMessage::Ack => vec![],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EchoAttack {
+    // Swap both public keys for p, so both sides end up with s = 0.
+    KeyFixing,
+    // Hand the server g = 1, p or p - 1 during negotiation.
+    GeneratorOne,
+    GeneratorP,
+    GeneratorPMinusOne,
+}
+
+// Mallory: recovers every echoed message and relays it, re-encrypting when the two sides
+// ended up with different keys.
+pub struct EchoMitm {
+    attack: EchoAttack,
+    p: Option<BigUint>,
+    server_public: Option<BigUint>,
+    client_key: Option<Vec<u8>>,
+    pub recovered: Vec<Vec<u8>>,
+}
+
+impl EchoMitm {
+    pub fn new(attack: EchoAttack) -> Self {
+        Self {
+            attack,
+            p: None,
+            server_public: None,
+            client_key: None,
+            recovered: Vec::new(),
+        }
+    }
+
+    fn malicious_g(&self, p: &BigUint) -> BigUint {
+        match self.attack {
+            EchoAttack::GeneratorOne => BigUint::from(1u32),
+            EchoAttack::GeneratorP | EchoAttack::KeyFixing => p.clone(),
+            EchoAttack::GeneratorPMinusOne => p - 1u32,
+        }
+    }
+
+    // With the forged generator the server's secret is g'^b, which is exactly its public key
+    // as the client's key was replaced by g' too.
+    fn server_secret(&self) -> Option<BigUint> {
+        match self.attack {
+            EchoAttack::KeyFixing => Some(BigUint::from(0u32)),
+            _ => self.server_public.clone(),
+        }
+    }
+
+    // The client computes B^a, and B is one of 0, 1 or p - 1 so there are only two options.
+    fn client_secrets(&self) -> Vec<BigUint> {
+        let (p, server_public) = match (&self.p, &self.server_public) {
+            (Some(p), Some(server_public)) => (p, server_public),
+            _ => return vec![BigUint::from(0u32)],
+        };
+
+        if self.attack == EchoAttack::GeneratorPMinusOne && server_public != &BigUint::from(1u32) {
+            return vec![BigUint::from(1u32), p - 1u32];
+        }
+
+        vec![server_public.clone()]
+    }
+
+    fn relay(&mut self, direction: Direction, ciphertext: &[u8], iv: &[u8]) -> Action<EchoMessage> {
+        let server_key = match self.server_secret() {
+            Some(secret) => derive_key_sha1(&secret),
+            None => return Action::Forward,
+        };
+
+        let (message, client_key) = match direction {
+            Direction::ToServer => {
+                let found = self.client_secrets().iter().find_map(|secret| {
+                    let key = derive_key_sha1(secret);
+                    decrypt_message(&key, ciphertext, iv).map(|message| (message, key))
+                });
+                match found {
+                    Some(found) => found,
+                    None => return Action::Forward,
+                }
+            }
+            Direction::ToClient => match (
+                decrypt_message(&server_key, ciphertext, iv),
+                self.client_key.clone(),
+            ) {
+                (Some(message), Some(client_key)) => (message, client_key),
+                _ => return Action::Forward,
+            },
+        };
+
+        let (from_key, to_key) = match direction {
+            Direction::ToServer => (&client_key, &server_key),
+            Direction::ToClient => (&server_key, &client_key),
+        };
+        let relayed = match from_key == to_key {
+            true => Action::Forward,
+            false => Action::Replace(EchoMessage::Encrypted {
+                ciphertext: cipher::cbc_mode_encrypt(&blocks::pkcs7_pad(16, &message), to_key, iv),
+                iv: iv.to_vec(),
+            }),
+        };
+
+        self.recovered.push(message);
+        self.client_key = Some(client_key);
+        relayed
+    }
+}
+
+impl Interceptor<EchoMessage> for EchoMitm {
+    fn intercept(&mut self, direction: Direction, message: &EchoMessage) -> Action<EchoMessage> {
+        let key_fixing = self.attack == EchoAttack::KeyFixing;
+
+        match (direction, message) {
+            (Direction::ToServer, EchoMessage::Hello { p, g, public }) => {
+                self.p = Some(p.clone());
+                Action::Replace(EchoMessage::Hello {
+                    p: p.clone(),
+                    g: g.clone(),
+                    public: if key_fixing {
+                        p.clone()
+                    } else {
+                        public.clone()
+                    },
+                })
+            }
+            (Direction::ToServer, EchoMessage::Negotiate { p, .. }) if !key_fixing => {
+                self.p = Some(p.clone());
+                Action::Replace(EchoMessage::Negotiate {
+                    p: p.clone(),
+                    g: self.malicious_g(p),
+                })
+            }
+            (Direction::ToServer, EchoMessage::Negotiate { p, .. }) => {
+                self.p = Some(p.clone());
+                Action::Forward
+            }
+            (Direction::ToServer, EchoMessage::PublicKey(_)) => match &self.p {
+                Some(p) if key_fixing => Action::Replace(EchoMessage::PublicKey(p.clone())),
+                Some(p) => Action::Replace(EchoMessage::PublicKey(self.malicious_g(p) % p)),
+                None => Action::Forward,
+            },
+            (Direction::ToClient, EchoMessage::PublicKey(public)) => match (&self.p, key_fixing) {
+                (Some(p), true) => Action::Replace(EchoMessage::PublicKey(p.clone())),
+                _ => {
+                    self.server_public = Some(public.clone());
+                    Action::Forward
+                }
+            },
+            (_, EchoMessage::Encrypted { ciphertext, iv }) => {
+                let (ciphertext, iv) = (ciphertext.clone(), iv.clone());
+                self.relay(direction, &ciphertext, &iv)
+            }
+            _ => Action::Forward,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cipher;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn big(n: u32) -> BigUint {
+        BigUint::from(n)
+    }
+
+    mod groups {
+        use super::*;
+
+        #[test]
+        fn named_groups_are_safe_prime_groups() {
+            for (bits, group) in [
+                (1536, Group::nist()),
+                (2048, Group::modp_2048()),
+                (3072, Group::modp_3072()),
+                (2048, Group::ffdhe_2048()),
+                (3072, Group::ffdhe_3072()),
+            ]
+            .iter()
+            {
+                let q = group.q.as_ref().unwrap();
+
+                assert_eq!(*bits, bignum::bits(&group.p));
+                assert_eq!(group.p, q * 2u32 + 1u32);
+                assert_eq!(big(1), bignum::modpow(&group.g, q, &group.p));
+            }
+        }
+
+        #[test]
+        fn nist_prime_is_modp_1536() {
+            assert_eq!(Group::nist(), Group::modp_1536());
+            assert!(bignum::to_hex(&Group::nist().p).ends_with(b"ca237327ffffffffffffffff"));
+        }
+    }
+
+    mod exchange {
+        use super::*;
+
+        #[test]
+        fn small_group() {
+            let group = Group::new(big(37), big(5), None);
+            let mut rng = StdRng::seed_from_u64(33);
+
+            let alice = KeyPair::generate(&group, &mut rng);
+            let bob = KeyPair::generate(&group, &mut rng);
+
+            assert!(alice.private >= big(1) && alice.private < big(36));
+            assert_eq!(
+                shared_secret_unchecked(&group, &alice.private, &bob.public),
+                shared_secret_unchecked(&group, &bob.private, &alice.public)
+            );
+        }
+
+        #[test]
+        fn seeded_rng_is_reproducible() {
+            let group = Group::nist();
+
+            let first = KeyPair::generate(&group, &mut StdRng::seed_from_u64(7));
+            let second = KeyPair::generate(&group, &mut StdRng::seed_from_u64(7));
+
+            assert_eq!(first, second);
+            assert!(&first.private < group.q.as_ref().unwrap());
+        }
+
+        #[test]
+        fn derived_keys_feed_cbc() {
+            let group = Group::modp_2048();
+            let mut rng = StdRng::seed_from_u64(2048);
+            let alice = KeyPair::generate(&group, &mut rng);
+            let bob = KeyPair::generate(&group, &mut rng);
+
+            let alice_key =
+                derive_key_sha256(&shared_secret(&group, &alice.private, &bob.public).unwrap());
+            let bob_key =
+                derive_key_sha256(&shared_secret(&group, &bob.private, &alice.public).unwrap());
+            assert_eq!(AES_KEY_SIZE, alice_key.len());
+
+            let iv = [0; 16];
+            let message = b"Diffie-Hellman keyed AES-CBC!!!!";
+            let ciphertext = cipher::cbc_mode_encrypt(message, &alice_key, &iv);
+
+            assert_eq!(
+                message.to_vec(),
+                cipher::cbc_mode_decrypt(&ciphertext, &bob_key, &iv)
+            );
+        }
+    }
+
+    mod validation {
+        use super::*;
+
+        #[test]
+        fn rejects_degenerate_keys() {
+            let group = Group::nist();
+            let p_minus_one = &group.p - 1u32;
+
+            for public in [big(0), big(1), p_minus_one, group.p.clone()].iter() {
+                assert_eq!(
+                    Err(DhError::OutOfRange),
+                    validate_public_key(&group, public, false)
+                );
+            }
+        }
+
+        #[test]
+        fn subgroup_check() {
+            // 2 is a quadratic residue mod 23 while 5 generates the whole group.
+            let group = Group::new(big(23), big(2), Some(big(11)));
+
+            assert_eq!(Ok(()), validate_public_key(&group, &big(4), true));
+            assert_eq!(Ok(()), validate_public_key(&group, &big(5), false));
+            assert_eq!(
+                Err(DhError::NotInSubgroup),
+                validate_public_key(&group, &big(5), true)
+            );
+            assert_eq!(
+                Err(DhError::NotInSubgroup),
+                shared_secret(&group, &big(3), &big(5))
+            );
+        }
+
+        #[test]
+        fn key_derivation_truncates_digest() {
+            let secret = big(0x0102_0304);
+
+            assert_eq!(
+                Sha1::digest(&[1, 2, 3, 4])[..16].to_vec(),
+                derive_key_sha1(&secret)
+            );
+            assert_eq!(
+                Sha256::digest(&[1, 2, 3, 4])[..16].to_vec(),
+                derive_key_sha256(&secret)
+            );
+        }
+    }
+
+    mod echo {
+        use super::*;
+        use crate::network::{self, Passthrough};
+
+        const MESSAGE: &[u8] = b"Play that funky music, white boy";
+
+        fn client(seed: u64) -> EchoClient<StdRng> {
+            EchoClient::new(Group::nist(), MESSAGE, StdRng::seed_from_u64(seed))
+        }
+
+        fn server(seed: u64) -> EchoServer<StdRng> {
+            EchoServer::new(StdRng::seed_from_u64(seed))
+        }
+
+        #[test]
+        fn echoes_without_interference() {
+            for negotiate in [false, true].iter() {
+                let mut alice = match negotiate {
+                    true => client(1).negotiating(),
+                    false => client(1),
+                };
+                let mut bob = server(2);
+
+                let transcript = network::run(&mut alice, &mut bob, &mut Passthrough, 10);
+
+                assert_eq!(Some(MESSAGE.to_vec()), alice.echo);
+                assert_eq!(vec![MESSAGE.to_vec()], bob.received);
+                assert_eq!(
+                    transcript.sent(Direction::ToServer),
+                    transcript.delivered(Direction::ToServer)
+                );
+            }
+        }
+
+        #[test]
+        fn key_fixing_recovers_both_directions() {
+            let (mut alice, mut bob) = (client(3), server(4));
+            let mut mallory = EchoMitm::new(EchoAttack::KeyFixing);
+
+            let transcript = network::run(&mut alice, &mut bob, &mut mallory, 10);
+
+            assert_eq!(Some(MESSAGE.to_vec()), alice.echo);
+            assert_eq!(vec![MESSAGE.to_vec(), MESSAGE.to_vec()], mallory.recovered);
+            assert_eq!(
+                vec![&EchoMessage::PublicKey(Group::nist().p)],
+                transcript.delivered(Direction::ToClient)[..1].to_vec()
+            );
+        }
+
+        #[test]
+        fn malicious_generators_stay_transparent() {
+            let attacks = [
+                EchoAttack::GeneratorOne,
+                EchoAttack::GeneratorP,
+                EchoAttack::GeneratorPMinusOne,
+            ];
+
+            for (seed, attack) in (0..8).zip(attacks.iter().cycle()) {
+                let mut alice = client(seed).negotiating();
+                let mut bob = server(seed + 100);
+                let mut mallory = EchoMitm::new(*attack);
+
+                network::run(&mut alice, &mut bob, &mut mallory, 10);
+
+                assert_eq!(Some(MESSAGE.to_vec()), alice.echo, "{:?}", attack);
+                assert_eq!(vec![MESSAGE.to_vec()], bob.received, "{:?}", attack);
+                assert_eq!(
+                    vec![MESSAGE.to_vec(), MESSAGE.to_vec()],
+                    mallory.recovered,
+                    "{:?}",
+                    attack
+                );
+            }
+        }
+
+        #[test]
+        fn validating_server_hangs_up() {
+            for attack in [EchoAttack::KeyFixing, EchoAttack::GeneratorOne].iter() {
+                let mut alice = client(5).negotiating();
+                let mut bob = server(6).validating();
+                let mut mallory = EchoMitm::new(*attack);
+
+                network::run(&mut alice, &mut bob, &mut mallory, 10);
+
+                assert_eq!(Some(DhError::OutOfRange), bob.error);
+                assert_eq!(None, alice.echo);
+                assert!(mallory.recovered.is_empty());
+            }
+        }
+    }
+}
@@ -0,0 +1,676 @@
+use super::bignum::{self, BigUint, RandBigInt};
+use super::hash::Hash;
+use super::hex;
+use super::sha1::Sha1;
+use num_traits::Zero;
+use rand::Rng;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+// Domain parameters from challenge 43.
+const CHALLENGE_P: &str = concat!(
+    "800000000000000089e1855218a0e7dac38136ffafa72eda7859f2171e25e65eac698c1702578b07dc2a1076da241c76",
+    "c62d374d8389ea5aeffd3226a0530cc565f3bf6b50929139ebeac04f48c3c84afb796d61e5a4f9a8fda812ab59494232",
+    "c7d2b4deb50aa18ee9e132bfa85ac4374d7f9091abc3d015efc871a584471bb1",
+);
+
+const CHALLENGE_Q: &str = "f4f47f05794b256174bba6e9b396a7707e563c5b";
+
+const CHALLENGE_G: &str = concat!(
+    "5958c9d3898b224b12672c0b98e06c60df923cb8bc999d119458fef538b8fa4046c8db53039db620c094c9fa077ef389",
+    "b5322a559946a71903f990f1f7e0e025e2d7f7cf494aff1a0470f5b64c36b625a097f1651fe775323556fe00b3608c88",
+    "7892878480e99041be601a62166ca6894bdd41a7054ec89f756ba9fc95302291",
+);
+
+#[derive(Debug, PartialEq)]
+pub enum DsaError {
+    // A record in a signed message list is missing a field or has one that doesn't parse.
+    MalformedRecord(usize),
+    // A record's `m:` line isn't the SHA-1 of its message.
+    HashMismatch(usize),
+    // g doesn't generate a subgroup of order q in Z_p*, or q doesn't divide p - 1.
+    InvalidParameters,
+    // A signed message list couldn't be read, with the reason.
+    Unreadable(String),
+}
+
+impl fmt::Display for DsaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DsaError::MalformedRecord(index) => write!(f, "Record {} is malformed", index),
+            DsaError::HashMismatch(index) => {
+                write!(f, "Record {} doesn't match its message hash", index)
+            }
+            DsaError::InvalidParameters => write!(f, "Domain parameters are inconsistent"),
+            DsaError::Unreadable(reason) => write!(f, "Can't read signed messages: {}", reason),
+        }
+    }
+}
+
+impl Error for DsaError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Parameters {
+    pub p: BigUint,
+    pub q: BigUint,
+    pub g: BigUint,
+}
+
+impl Parameters {
+    pub fn new(p: BigUint, q: BigUint, g: BigUint) -> Self {
+        Self { p, q, g }
+    }
+
+    pub fn challenge() -> Self {
+        Self::new(
+            bignum::from_hex(CHALLENGE_P.as_bytes()),
+            bignum::from_hex(CHALLENGE_Q.as_bytes()),
+            bignum::from_hex(CHALLENGE_G.as_bytes()),
+        )
+    }
+
+    // Structural checks only, p and q aren't tested for primality: q divides p - 1 and g is a
+    // non-trivial element of order q. Rules out g = 0, g = 1 and g = p + 1.
+    pub fn validate(&self) -> Result<(), DsaError> {
+        let one = BigUint::from(1u32);
+        if self.p <= one || self.q <= one || !((&self.p - 1u32) % &self.q).is_zero() {
+            return Err(DsaError::InvalidParameters);
+        }
+        if self.g <= one || self.g >= self.p || bignum::modpow(&self.g, &self.q, &self.p) != one {
+            return Err(DsaError::InvalidParameters);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyPair {
+    pub private: BigUint,
+    pub public: BigUint,
+}
+
+impl KeyPair {
+    pub fn generate<R: Rng>(params: &Parameters, rng: &mut R) -> Self {
+        let private = rng.gen_biguint_range(&BigUint::from(1u32), &params.q);
+        let public = public_key(params, &private);
+
+        Self { private, public }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Signature {
+    pub r: BigUint,
+    pub s: BigUint,
+}
+
+pub fn public_key(params: &Parameters, private: &BigUint) -> BigUint {
+    bignum::modpow(&params.g, private, &params.p)
+}
+
+// SHA-1 of the message as an integer. q is 160 bits as well, so no truncation is needed.
+pub fn hash_message(message: &[u8]) -> BigUint {
+    bignum::from_bytes_be(&Sha1::digest(message))
+}
+
+// Draws fresh nonces until neither half of the signature is zero. Parameters are validated
+// first, as with g = 0 every r is zero and this would never return.
+pub fn sign<R: Rng>(
+    params: &Parameters,
+    private: &BigUint,
+    message: &[u8],
+    rng: &mut R,
+) -> Result<Signature, DsaError> {
+    params.validate()?;
+    let hash = hash_message(message);
+
+    loop {
+        let k = rng.gen_biguint_range(&BigUint::from(1u32), &params.q);
+        if let Some(signature) = sign_with_nonce(params, private, &hash, &k) {
+            return Ok(signature);
+        }
+    }
+}
+
+// DON'T USE: signs with whatever parameters it's handed and lets a zero r through.
+pub fn sign_unchecked<R: Rng>(
+    params: &Parameters,
+    private: &BigUint,
+    message: &[u8],
+    rng: &mut R,
+) -> Signature {
+    let q = &params.q;
+    let hash = hash_message(message);
+
+    loop {
+        let k = rng.gen_biguint_range(&BigUint::from(1u32), q);
+        if let Some(k_inv) = bignum::modinv(&k, q) {
+            let r = bignum::modpow(&params.g, &k, &params.p) % q;
+            let s = k_inv * (&hash + private * &r) % q;
+            return Signature { r, s };
+        }
+    }
+}
+
+// r = (g^k mod p) mod q and s = k^-1 (H + x r) mod q. Fails for the rare nonce that makes
+// either of them zero, or one that isn't invertible mod q.
+pub fn sign_with_nonce(
+    params: &Parameters,
+    private: &BigUint,
+    hash: &BigUint,
+    k: &BigUint,
+) -> Option<Signature> {
+    let q = &params.q;
+    let r = bignum::modpow(&params.g, k, &params.p) % q;
+    let k_inv = bignum::modinv(k, q)?;
+    let s = k_inv * (hash + private * &r) % q;
+    if r.is_zero() || s.is_zero() {
+        return None;
+    }
+
+    Some(Signature { r, s })
+}
+
+// Checks 0 < r, s < q, but trusts the domain parameters it's given.
+pub fn verify(
+    params: &Parameters,
+    public: &BigUint,
+    message: &[u8],
+    signature: &Signature,
+) -> bool {
+    let q = &params.q;
+    let Signature { r, s } = signature;
+    if r.is_zero() || r >= q || s.is_zero() || s >= q {
+        return false;
+    }
+
+    verify_unchecked(params, public, message, signature)
+}
+
+// DON'T USE: skips the range checks on r and s, so with g = 0 the signature (0, s) verifies
+// for every message.
+pub fn verify_unchecked(
+    params: &Parameters,
+    public: &BigUint,
+    message: &[u8],
+    signature: &Signature,
+) -> bool {
+    let q = &params.q;
+    let Signature { r, s } = signature;
+    let w = match bignum::modinv(s, q) {
+        Some(w) => w,
+        None => return false,
+    };
+    let u1 = hash_message(message) * &w % q;
+    let u2 = r * &w % q;
+    let v = bignum::modpow(&params.g, &u1, &params.p) * bignum::modpow(public, &u2, &params.p)
+        % &params.p
+        % q;
+
+    &v == r
+}
+
+// Validates the domain parameters and that the public key lies in the order q subgroup
+// before verifying, so substituted parameters are rejected outright.
+pub fn verify_validated(
+    params: &Parameters,
+    public: &BigUint,
+    message: &[u8],
+    signature: &Signature,
+) -> bool {
+    if params.validate().is_err() {
+        return false;
+    }
+
+    let one = BigUint::from(1u32);
+    if public <= &one || public >= &params.p || bignum::modpow(public, &params.q, &params.p) != one
+    {
+        return false;
+    }
+
+    verify(params, public, message, signature)
+}
+
+// With g = p + 1 every power of g is 1 mod p, so v = y^u2 = y^(r/s). Picking r = y^z and
+// s = r/z for any z gives a signature that verifies for every message.
+pub fn forge_magic_signature<R: Rng>(
+    params: &Parameters,
+    public: &BigUint,
+    rng: &mut R,
+) -> Signature {
+    let q = &params.q;
+
+    loop {
+        let z = rng.gen_biguint_range(&BigUint::from(1u32), q);
+        let r = bignum::modpow(public, &z, &params.p) % q;
+        if let Some(z_inv) = bignum::modinv(&z, q) {
+            let s = &r * z_inv % q;
+            if !r.is_zero() && !s.is_zero() {
+                return Signature { r, s };
+            }
+        }
+    }
+}
+
+// With the nonce known, s k = H + x r mod q gives x = (s k - H) r^-1 mod q.
+pub fn private_key_from_nonce(
+    params: &Parameters,
+    hash: &BigUint,
+    signature: &Signature,
+    k: &BigUint,
+) -> Option<BigUint> {
+    let q = &params.q;
+    let r_inv = bignum::modinv(&signature.r, q)?;
+
+    Some(sub_mod(&(&signature.s * k), hash, q) * r_inv % q)
+}
+
+// Walks g^k for every k in `nonces`, one multiplication per step, until it lands on r. The
+// candidate key is then checked against the public key.
+pub fn recover_key_from_nonce_range(
+    params: &Parameters,
+    public: &BigUint,
+    hash: &BigUint,
+    signature: &Signature,
+    nonces: Range<u64>,
+) -> Option<BigUint> {
+    let start = BigUint::from(nonces.start);
+    let mut power = bignum::modpow(&params.g, &start, &params.p);
+
+    for k in nonces {
+        if &power % &params.q == signature.r {
+            let private = private_key_from_nonce(params, hash, signature, &BigUint::from(k))?;
+            if &public_key(params, &private) == public {
+                return Some(private);
+            }
+        }
+        power = power * &params.g % &params.p;
+    }
+
+    None
+}
+
+// The challenge's format: SHA-1 of the private key in lowercase hex without leading zeros.
+pub fn fingerprint(private: &BigUint) -> Vec<u8> {
+    hex::encode(&Sha1::digest(format!("{:x}", private).as_bytes()))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignedMessage {
+    pub message: Vec<u8>,
+    pub hash: BigUint,
+    pub signature: Signature,
+}
+
+// Parses the challenge 44 format: groups of `msg:`, `s:` and `r:` lines, with s and r in
+// decimal, and an `m:` line holding the message's SHA-1 in hex.
+pub fn parse_signed_messages(text: &str) -> Result<Vec<SignedMessage>, DsaError> {
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    if !lines.len().is_multiple_of(4) {
+        return Err(DsaError::MalformedRecord(lines.len() / 4));
+    }
+
+    lines
+        .chunks(4)
+        .enumerate()
+        .map(|(index, record)| {
+            let malformed = DsaError::MalformedRecord(index);
+            let message = field(record[0], "msg")
+                .ok_or(malformed)?
+                .as_bytes()
+                .to_vec();
+            let s = number(record[1], "s", 10).ok_or(DsaError::MalformedRecord(index))?;
+            let r = number(record[2], "r", 10).ok_or(DsaError::MalformedRecord(index))?;
+            let hash = number(record[3], "m", 16).ok_or(DsaError::MalformedRecord(index))?;
+            if hash != hash_message(&message) {
+                return Err(DsaError::HashMismatch(index));
+            }
+
+            Ok(SignedMessage {
+                message,
+                hash,
+                signature: Signature { r, s },
+            })
+        })
+        .collect()
+}
+
+pub fn load_signed_messages<P: AsRef<Path>>(path: P) -> Result<Vec<SignedMessage>, DsaError> {
+    let text = fs::read_to_string(path).map_err(|error| DsaError::Unreadable(error.to_string()))?;
+
+    parse_signed_messages(&text)
+}
+
+fn field<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    line.strip_prefix(name)?.strip_prefix(": ")
+}
+
+fn number(line: &str, name: &str, radix: u32) -> Option<BigUint> {
+    BigUint::parse_bytes(field(line, name)?.trim().as_bytes(), radix)
+}
+
+// Signatures made with the same nonce share r, as r only depends on k.
+pub fn find_repeated_nonces(messages: &[SignedMessage]) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    for (i, a) in messages.iter().enumerate() {
+        for (j, b) in messages.iter().enumerate().skip(i + 1) {
+            if a.signature.r == b.signature.r {
+                pairs.push((i, j));
+            }
+        }
+    }
+
+    pairs
+}
+
+// Subtracting the two signing equations eliminates x: k = (H1 - H2) / (s1 - s2) mod q.
+pub fn nonce_from_repeated_nonce(
+    params: &Parameters,
+    a: &SignedMessage,
+    b: &SignedMessage,
+) -> Option<BigUint> {
+    let q = &params.q;
+    let s_diff_inv = bignum::modinv(&sub_mod(&a.signature.s, &b.signature.s, q), q)?;
+
+    Some(sub_mod(&a.hash, &b.hash, q) * s_diff_inv % q)
+}
+
+// Tries every pair that shares a nonce and keeps the first key that matches `public`.
+pub fn recover_key_from_repeated_nonce(
+    params: &Parameters,
+    public: &BigUint,
+    messages: &[SignedMessage],
+) -> Option<BigUint> {
+    find_repeated_nonces(messages)
+        .into_iter()
+        .filter_map(|(i, j)| {
+            let (a, b) = (&messages[i], &messages[j]);
+            let k = nonce_from_repeated_nonce(params, a, b)?;
+            private_key_from_nonce(params, &a.hash, &a.signature, &k)
+        })
+        .find(|private| &public_key(params, private) == public)
+}
+
+fn sub_mod(a: &BigUint, b: &BigUint, modulus: &BigUint) -> BigUint {
+    (a % modulus + modulus - b % modulus) % modulus
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn key_pair(seed: u64) -> KeyPair {
+        KeyPair::generate(&Parameters::challenge(), &mut StdRng::seed_from_u64(seed))
+    }
+
+    mod signatures {
+        use super::*;
+
+        #[test]
+        fn challenge_parameters_are_consistent() {
+            let params = Parameters::challenge();
+
+            assert_eq!(
+                BigUint::zero(),
+                (&params.p - 1u32) % &params.q,
+                "q must divide p - 1"
+            );
+            assert_eq!(
+                BigUint::from(1u32),
+                bignum::modpow(&params.g, &params.q, &params.p)
+            );
+        }
+
+        #[test]
+        fn sign_and_verify() {
+            let params = Parameters::challenge();
+            let other = key_pair(44);
+            let key_pair = key_pair(43);
+            let mut rng = StdRng::seed_from_u64(1);
+            let signature = sign(&params, &key_pair.private, b"hi mom", &mut rng).unwrap();
+
+            assert!(verify(&params, &key_pair.public, b"hi mom", &signature));
+            assert!(!verify(&params, &key_pair.public, b"hi dad", &signature));
+            assert!(!verify(&params, &other.public, b"hi mom", &signature));
+        }
+
+        #[test]
+        fn rejects_out_of_range_signatures() {
+            let params = Parameters::challenge();
+            let key_pair = key_pair(43);
+            let signature = sign(
+                &params,
+                &key_pair.private,
+                b"hi mom",
+                &mut StdRng::seed_from_u64(1),
+            )
+            .unwrap();
+
+            let shifted = Signature {
+                r: &signature.r + &params.q,
+                s: signature.s.clone(),
+            };
+            let zero = Signature {
+                r: BigUint::zero(),
+                s: signature.s,
+            };
+            assert!(!verify(&params, &key_pair.public, b"hi mom", &shifted));
+            assert!(!verify(&params, &key_pair.public, b"hi mom", &zero));
+        }
+
+        #[test]
+        fn fingerprint_drops_leading_zeros() {
+            // Python's '%x' % 0x0abc is "abc", not "0abc".
+            assert_eq!(
+                hex::encode(&Sha1::digest(b"abc")),
+                fingerprint(&BigUint::from(0x0abcu32))
+            );
+        }
+    }
+
+    mod tampering {
+        use super::*;
+
+        fn with_generator(g: BigUint) -> Parameters {
+            let challenge = Parameters::challenge();
+            Parameters::new(challenge.p, challenge.q, g)
+        }
+
+        #[test]
+        fn validates_parameters() {
+            let challenge = Parameters::challenge();
+
+            assert_eq!(Ok(()), challenge.validate());
+            for g in [
+                BigUint::zero(),
+                BigUint::from(1u32),
+                &challenge.p + 1u32,
+                &challenge.p - 1u32,
+            ]
+            .iter()
+            {
+                assert_eq!(
+                    Err(DsaError::InvalidParameters),
+                    with_generator(g.clone()).validate()
+                );
+            }
+        }
+
+        #[test]
+        fn zero_generator_signs_everything() {
+            let params = with_generator(BigUint::zero());
+            let key_pair = key_pair(45);
+            let mut rng = StdRng::seed_from_u64(1);
+            let signature = sign_unchecked(&params, &key_pair.private, b"Hello, world", &mut rng);
+
+            assert_eq!(BigUint::zero(), signature.r);
+            for message in [&b"Hello, world"[..], b"Goodbye, world"].iter() {
+                assert!(verify_unchecked(
+                    &params,
+                    &key_pair.public,
+                    message,
+                    &signature
+                ));
+                assert!(!verify(&params, &key_pair.public, message, &signature));
+            }
+            assert_eq!(
+                Err(DsaError::InvalidParameters),
+                sign(&params, &key_pair.private, b"Hello, world", &mut rng)
+            );
+        }
+
+        #[test]
+        fn magic_signatures_verify_everything() {
+            let params = with_generator(&Parameters::challenge().p + 1u32);
+            let public = key_pair(45).public;
+            let signature = forge_magic_signature(&params, &public, &mut StdRng::seed_from_u64(1));
+
+            for message in [&b"Hello, world"[..], b"Goodbye, world"].iter() {
+                assert!(verify(&params, &public, message, &signature));
+                assert!(!verify_validated(&params, &public, message, &signature));
+            }
+        }
+
+        #[test]
+        fn validated_verifier_accepts_real_signatures() {
+            let params = Parameters::challenge();
+            let key_pair = key_pair(45);
+            let mut rng = StdRng::seed_from_u64(1);
+            let signature = sign(&params, &key_pair.private, b"hi mom", &mut rng).unwrap();
+
+            assert!(verify_validated(
+                &params,
+                &key_pair.public,
+                b"hi mom",
+                &signature
+            ));
+            assert!(!verify_validated(
+                &params,
+                &BigUint::from(1u32),
+                b"hi mom",
+                &signature
+            ));
+        }
+    }
+
+    mod nonces {
+        use super::*;
+
+        #[test]
+        fn known_nonce_reveals_the_key() {
+            let params = Parameters::challenge();
+            let key_pair = key_pair(43);
+            let hash = hash_message(b"hi mom");
+            let k = BigUint::from(31337u32);
+            let signature = sign_with_nonce(&params, &key_pair.private, &hash, &k).unwrap();
+
+            assert_eq!(
+                Some(key_pair.private.clone()),
+                private_key_from_nonce(&params, &hash, &signature, &k)
+            );
+            assert_eq!(
+                Some(key_pair.private.clone()),
+                recover_key_from_nonce_range(
+                    &params,
+                    &key_pair.public,
+                    &hash,
+                    &signature,
+                    31000..32000
+                )
+            );
+            assert_eq!(
+                None,
+                recover_key_from_nonce_range(&params, &key_pair.public, &hash, &signature, 0..1000)
+            );
+        }
+
+        #[test]
+        fn repeated_nonce_reveals_the_key() {
+            let params = Parameters::challenge();
+            let key_pair = key_pair(43);
+            let k = BigUint::from(0xdead_beef_u32);
+            let messages: Vec<SignedMessage> = [&b"hi mom"[..], b"hi dad", b"hi sis"]
+                .iter()
+                .zip([k.clone(), BigUint::from(7u32), k].iter())
+                .map(|(message, k)| {
+                    let hash = hash_message(message);
+                    SignedMessage {
+                        message: message.to_vec(),
+                        signature: sign_with_nonce(&params, &key_pair.private, &hash, k).unwrap(),
+                        hash,
+                    }
+                })
+                .collect();
+
+            assert_eq!(vec![(0, 2)], find_repeated_nonces(&messages));
+            assert_eq!(
+                Some(key_pair.private.clone()),
+                recover_key_from_repeated_nonce(&params, &key_pair.public, &messages)
+            );
+            assert_eq!(
+                None,
+                recover_key_from_repeated_nonce(&params, &key_pair.public, &messages[..2])
+            );
+        }
+    }
+
+    mod parsing {
+        use super::*;
+
+        #[test]
+        fn parses_records() {
+            let text = concat!(
+                "msg: hi mom \n",
+                "s: 12\n",
+                "r: 34\n",
+                "m: 2ecf0d1fa1e1a0bbbb0d7fb85b6c5a2d6f7a4c3a\n",
+            );
+
+            assert_eq!(Err(DsaError::HashMismatch(0)), parse_signed_messages(text));
+
+            let hash = hex::encode(&Sha1::digest(b"hi mom "));
+            let text = format!(
+                "msg: hi mom \ns: 12\nr: 34\nm: {}\n",
+                String::from_utf8(hash).unwrap()
+            );
+            let messages = parse_signed_messages(&text).unwrap();
+
+            assert_eq!(1, messages.len());
+            assert_eq!(b"hi mom ".to_vec(), messages[0].message);
+            assert_eq!(BigUint::from(12u32), messages[0].signature.s);
+            assert_eq!(BigUint::from(34u32), messages[0].signature.r);
+        }
+
+        #[test]
+        fn loads_challenge_file() {
+            let messages = load_signed_messages("../challenge-data/44.txt").unwrap();
+            assert_eq!(11, messages.len());
+
+            assert!(matches!(
+                load_signed_messages("../challenge-data/missing.txt"),
+                Err(DsaError::Unreadable(_))
+            ));
+        }
+
+        #[test]
+        fn rejects_malformed_records() {
+            assert_eq!(
+                Err(DsaError::MalformedRecord(0)),
+                parse_signed_messages("msg: hi\ns: 12\nr: 34\n")
+            );
+            assert_eq!(
+                Err(DsaError::MalformedRecord(0)),
+                parse_signed_messages("msg: hi\ns: twelve\nr: 34\nm: 00\n")
+            );
+        }
+    }
+}
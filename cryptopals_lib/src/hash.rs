@@ -0,0 +1,253 @@
+use std::marker::PhantomData;
+
+pub trait Hash: Sized {
+    const BLOCK_SIZE: usize;
+    const OUTPUT_SIZE: usize;
+
+    fn new() -> Self;
+
+    fn update(&mut self, data: &[u8]);
+
+    fn finalize(self) -> Vec<u8>;
+
+    // Exports the chaining value and the number of bytes it covers. Bytes still waiting for a
+    // full block are not part of the exported state.
+    fn export_state(&self) -> (Vec<u8>, u64);
+
+    // Restores a chaining value as if `length` bytes of padded message had already been
+    // processed. Returns None when `state` isn't a whole chaining value.
+    fn import_state(state: &[u8], length: u64) -> Option<Self>;
+
+    fn padding(length: u64) -> Vec<u8>;
+
+    // Truncated digests like SHA-224's don't carry the full chaining value, so there is
+    // nothing to resume from and this returns None.
+    fn from_digest(digest: &[u8], length: u64) -> Option<Self> {
+        Self::import_state(digest, length)
+    }
+
+    fn digest(data: &[u8]) -> Vec<u8> {
+        let mut hash = Self::new();
+        hash.update(data);
+        hash.finalize()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Endian {
+    Big,
+    Little,
+}
+
+impl Endian {
+    pub fn read_word(self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        match self {
+            Endian::Big => u32::from_be_bytes(bytes),
+            Endian::Little => u32::from_le_bytes(bytes),
+        }
+    }
+
+    pub fn write_word(self, word: u32) -> [u8; 4] {
+        match self {
+            Endian::Big => word.to_be_bytes(),
+            Endian::Little => word.to_le_bytes(),
+        }
+    }
+
+    pub fn read_block(self, block: &[u8]) -> [u32; 16] {
+        let mut words = [0u32; 16];
+        for (word, bytes) in words.iter_mut().zip(block.chunks(4)) {
+            *word = self.read_word(bytes);
+        }
+
+        words
+    }
+
+    fn write_length(self, bit_length: u64, length_size: usize) -> Vec<u8> {
+        let mut bytes = vec![0; length_size];
+        match self {
+            Endian::Big => bytes[length_size - 8..].copy_from_slice(&bit_length.to_be_bytes()),
+            Endian::Little => bytes[..8].copy_from_slice(&bit_length.to_le_bytes()),
+        }
+
+        bytes
+    }
+}
+
+pub trait Compression {
+    type State: Copy + AsRef<[u32]> + AsMut<[u32]>;
+
+    const INITIAL_STATE: Self::State;
+    const ENDIAN: Endian;
+    const BLOCK_SIZE: usize = 64;
+    // Number of bytes the message bit length is encoded into at the end of the padding.
+    const LENGTH_SIZE: usize = 8;
+    // Number of state words emitted as the digest, for truncated variants like SHA-224.
+    const OUTPUT_WORDS: usize;
+
+    fn compress(state: &mut Self::State, block: &[u8]);
+}
+
+pub struct MerkleDamgard<C: Compression> {
+    state: C::State,
+    buffer: Vec<u8>,
+    length: u64,
+    compression: PhantomData<C>,
+}
+
+impl<C: Compression> Default for MerkleDamgard<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Compression> MerkleDamgard<C> {
+    pub fn new() -> Self {
+        Self::from_state(C::INITIAL_STATE, 0)
+    }
+
+    // `length` is the number of bytes already fed through the compression function, so it
+    // should be a multiple of the block size.
+    pub fn from_state(state: C::State, length: u64) -> Self {
+        Self {
+            state,
+            buffer: Vec::with_capacity(C::BLOCK_SIZE),
+            length,
+            compression: PhantomData,
+        }
+    }
+
+    pub fn state(&self) -> C::State {
+        self.state
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.length += data.len() as u64;
+        self.buffer.extend_from_slice(data);
+
+        let full_blocks = self.buffer.len() - self.buffer.len() % C::BLOCK_SIZE;
+        for block in self.buffer[..full_blocks].chunks(C::BLOCK_SIZE) {
+            C::compress(&mut self.state, block);
+        }
+        self.buffer.drain(..full_blocks);
+    }
+
+    pub fn finalize(mut self) -> Vec<u8> {
+        let padding = md_padding::<C>(self.length);
+        self.update(&padding);
+
+        self.state.as_ref()[..C::OUTPUT_WORDS]
+            .iter()
+            .flat_map(|word| C::ENDIAN.write_word(*word).to_vec())
+            .collect()
+    }
+}
+
+impl<C: Compression> Hash for MerkleDamgard<C> {
+    const BLOCK_SIZE: usize = C::BLOCK_SIZE;
+    const OUTPUT_SIZE: usize = C::OUTPUT_WORDS * 4;
+
+    fn new() -> Self {
+        MerkleDamgard::new()
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        MerkleDamgard::update(self, data)
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        MerkleDamgard::finalize(self)
+    }
+
+    fn export_state(&self) -> (Vec<u8>, u64) {
+        let state = self
+            .state
+            .as_ref()
+            .iter()
+            .flat_map(|word| C::ENDIAN.write_word(*word).to_vec())
+            .collect();
+
+        (state, self.length - self.buffer.len() as u64)
+    }
+
+    fn import_state(state: &[u8], length: u64) -> Option<Self> {
+        let mut words = C::INITIAL_STATE;
+        if state.len() != words.as_ref().len() * 4 {
+            return None;
+        }
+
+        for (word, bytes) in words.as_mut().iter_mut().zip(state.chunks(4)) {
+            *word = C::ENDIAN.read_word(bytes);
+        }
+
+        Some(Self::from_state(words, length))
+    }
+
+    fn padding(length: u64) -> Vec<u8> {
+        md_padding::<C>(length)
+    }
+}
+
+pub fn md_padding<C: Compression>(length: u64) -> Vec<u8> {
+    let block_size = C::BLOCK_SIZE;
+    let used = (length as usize + C::LENGTH_SIZE) % block_size;
+    let zeros = (block_size - 1 - used) % block_size;
+
+    [
+        vec![0x80],
+        vec![0; zeros],
+        C::ENDIAN.write_length(length.wrapping_mul(8), C::LENGTH_SIZE),
+    ]
+    .concat()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::md4::Md4;
+    use crate::md5::Md5;
+    use crate::sha1::Sha1;
+    use crate::sha256::{Sha224, Sha256};
+
+    fn exported_state_resumes<H: Hash>() {
+        let data = b"Exporting the chaining value after a whole block of input, plus a short tail";
+
+        let mut original = H::new();
+        original.update(&data[..H::BLOCK_SIZE + 3]);
+        let (state, length) = original.export_state();
+
+        let mut resumed = H::import_state(&state, length).unwrap();
+        resumed.update(&data[length as usize..]);
+
+        assert_eq!(H::digest(data), resumed.finalize());
+    }
+
+    #[test]
+    fn export_import_round_trip() {
+        exported_state_resumes::<Md4>();
+        exported_state_resumes::<Md5>();
+        exported_state_resumes::<Sha1>();
+        exported_state_resumes::<Sha224>();
+        exported_state_resumes::<Sha256>();
+    }
+
+    #[test]
+    fn truncated_digest_does_not_resume() {
+        let digest = Sha224::digest(b"YELLOW SUBMARINE");
+
+        assert!(Sha224::from_digest(&digest, 64).is_none());
+        assert!(Sha256::from_digest(&Sha256::digest(b"YELLOW SUBMARINE"), 64).is_some());
+        assert!(Sha1::import_state(&[0; 19], 64).is_none());
+    }
+
+    #[test]
+    fn padding_endianness() {
+        let big = Sha1::padding(3);
+        let little = Md4::padding(3);
+
+        assert_eq!(61, big.len());
+        assert_eq!(vec![0, 0, 0, 0, 0, 0, 0, 24], big[53..].to_vec());
+        assert_eq!(vec![24, 0, 0, 0, 0, 0, 0, 0], little[53..].to_vec());
+    }
+}
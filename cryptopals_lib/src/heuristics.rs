@@ -116,6 +116,53 @@ fn normalize_distance(slice: &[u8], key_size: usize) -> u32 {
     nd.iter().sum::<u32>() / divisor
 }
 
+pub fn median(samples: &[f64]) -> f64 {
+    assert!(!samples.is_empty(), "Median of no samples");
+
+    let mut samples = samples.to_owned();
+    samples.sort_by(|a, b| a.partial_cmp(b).expect("Can't rank NaN samples"));
+
+    let middle = samples.len() / 2;
+    if samples.len().is_multiple_of(2) {
+        (samples[middle - 1] + samples[middle]) / 2.0
+    } else {
+        samples[middle]
+    }
+}
+
+// Drops `trim` of the samples from each end, so anything from half upwards leaves nothing.
+pub fn trimmed_mean(samples: &[f64], trim: f64) -> f64 {
+    assert!(
+        (0.0..0.5).contains(&trim),
+        "Trimmed mean needs 0 <= trim < 0.5, got {}",
+        trim
+    );
+    assert!(!samples.is_empty(), "Trimmed mean of no samples");
+
+    let mut samples = samples.to_owned();
+    samples.sort_by(|a, b| a.partial_cmp(b).expect("Can't rank NaN samples"));
+
+    let cut = (samples.len() as f64 * trim) as usize;
+    let kept = &samples[cut..samples.len() - cut];
+
+    kept.iter().sum::<f64>() / kept.len() as f64
+}
+
+pub fn welch_t_test(a: &[f64], b: &[f64]) -> f64 {
+    fn mean_and_variance(samples: &[f64]) -> (f64, f64) {
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+
+        (mean, variance)
+    }
+
+    let (mean_a, variance_a) = mean_and_variance(a);
+    let (mean_b, variance_b) = mean_and_variance(b);
+
+    (mean_a - mean_b) / (variance_a / a.len() as f64 + variance_b / b.len() as f64).sqrt()
+}
+
 #[cfg(test)]
 mod test {
     use super::super::xor;
@@ -195,4 +242,55 @@ mod test {
         assert_eq!(2, hamm_distance(&[4], &[8]));
         assert_eq!(3, hamm_distance(&[9], &[14]));
     }
+
+    #[test]
+    fn _median() {
+        assert_eq!(2.0, median(&[3.0, 1.0, 2.0]));
+        assert_eq!(2.5, median(&[4.0, 1.0, 3.0, 2.0]));
+        assert_eq!(5.0, median(&[5.0]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Median of no samples")]
+    fn median_rejects_no_samples() {
+        median(&[]);
+    }
+
+    #[test]
+    fn _trimmed_mean() {
+        assert_eq!(3.0, trimmed_mean(&[100.0, 2.0, 3.0, 4.0, -50.0], 0.2));
+        assert_eq!(10.0, trimmed_mean(&[10.0, 10.0, 10.0], 0.0));
+        assert_eq!(2.0, trimmed_mean(&[1.0, 3.0], 0.49));
+        assert_eq!(
+            49.5,
+            trimmed_mean(&(0..100).map(f64::from).collect::<Vec<_>>(), 0.49)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Trimmed mean needs 0 <= trim < 0.5")]
+    fn trimmed_mean_rejects_half_trim() {
+        trimmed_mean(&[1.0, 2.0, 3.0, 4.0], 0.5);
+    }
+
+    #[test]
+    #[should_panic(expected = "Trimmed mean needs 0 <= trim < 0.5")]
+    fn trimmed_mean_rejects_negative_trim() {
+        trimmed_mean(&[1.0, 2.0, 3.0, 4.0], -0.1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Trimmed mean of no samples")]
+    fn trimmed_mean_rejects_no_samples() {
+        trimmed_mean(&[], 0.2);
+    }
+
+    #[test]
+    fn _welch_t_test() {
+        let a = [19.8, 20.4, 19.6, 17.8, 18.5, 18.9, 18.3, 18.9, 19.5, 22.0];
+        let b = [28.2, 26.6, 20.1, 23.3, 25.2, 22.1, 17.7, 27.6, 20.6, 13.7];
+
+        assert!((welch_t_test(&a, &b) - -2.0740).abs() < 0.001);
+        assert!((welch_t_test(&b, &a) - 2.0740).abs() < 0.001);
+    }
 }
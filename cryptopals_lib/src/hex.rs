@@ -29,12 +29,22 @@ pub fn encode_with(buffer: Vec<u8>) -> impl Fn(&[u8]) -> Vec<u8> {
 
 pub fn decode(bytes: &[u8]) -> Vec<u8> {
     fn de_hex(byte: u8) -> u8 {
-        if byte > 57 {
+        if byte > 70 {
             return byte + 10 - 97;
         }
+        if byte > 57 {
+            return byte + 10 - 65;
+        }
         byte - 48
     }
 
+    // An odd number of digits is read as if it had a leading zero.
+    let bytes = if !bytes.len().is_multiple_of(2) {
+        [b"0", bytes].concat()
+    } else {
+        bytes.to_vec()
+    };
+
     bytes
         .chunks(2)
         .map(|b| {
@@ -109,5 +119,19 @@ mod test {
             assert_eq!(s.len(), decode(hex).len());
             assert_eq!(s, decode(hex))
         }
+
+        #[test]
+        fn uppercase_digits() {
+            let hex = b"48656C6C6F20576F726C64";
+            let s = b"Hello World".to_vec();
+
+            assert_eq!(s, decode(hex))
+        }
+
+        #[test]
+        fn odd_length() {
+            assert_eq!(vec![0x01, 0x00], decode(b"100"));
+            assert_eq!(vec![0x0f], decode(b"f"));
+        }
     }
 }
@@ -0,0 +1,125 @@
+use super::ct::ct_eq;
+use super::hash::Hash;
+
+pub struct Hmac<H: Hash> {
+    inner: H,
+    outer_key: Vec<u8>,
+}
+
+impl<H: Hash> Hmac<H> {
+    pub fn new(key: &[u8]) -> Self {
+        let key = block_sized_key::<H>(key);
+        let inner_key: Vec<u8> = key.iter().map(|byte| byte ^ 0x36).collect();
+        let outer_key: Vec<u8> = key.iter().map(|byte| byte ^ 0x5c).collect();
+
+        let mut inner = H::new();
+        inner.update(&inner_key);
+
+        Self { inner, outer_key }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    pub fn finalize(self) -> Vec<u8> {
+        let inner_hash = self.inner.finalize();
+        H::digest(&[self.outer_key, inner_hash].concat())
+    }
+}
+
+fn block_sized_key<H: Hash>(key: &[u8]) -> Vec<u8> {
+    let mut key = if key.len() > H::BLOCK_SIZE {
+        H::digest(key)
+    } else {
+        key.to_vec()
+    };
+    key.resize(H::BLOCK_SIZE, 0);
+
+    key
+}
+
+pub fn hmac<H: Hash>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut hmac = Hmac::<H>::new(key);
+    hmac.update(message);
+    hmac.finalize()
+}
+
+pub fn verify_hmac<H: Hash>(key: &[u8], message: &[u8], mac: &[u8]) -> bool {
+    ct_eq(&hmac::<H>(key, message), mac)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hex;
+    use crate::md5::Md5;
+    use crate::sha1::Sha1;
+    use crate::sha256::Sha256;
+
+    #[test]
+    fn rfc_2202_sha1_vectors() {
+        assert_eq!(
+            b"b617318655057264e28bc0b6fb378c8ef146be00".to_vec(),
+            hex::encode(&hmac::<Sha1>(&[0x0b; 20], b"Hi There"))
+        );
+        assert_eq!(
+            b"effcdf6ae5eb2fa2d27416d5f184df9c259a7c79".to_vec(),
+            hex::encode(&hmac::<Sha1>(b"Jefe", b"what do ya want for nothing?"))
+        );
+        assert_eq!(
+            b"aa4ae5e15272d00e95705637ce8a3b55ed402112".to_vec(),
+            hex::encode(&hmac::<Sha1>(
+                &[0xaa; 80],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            ))
+        );
+    }
+
+    #[test]
+    fn rfc_4231_sha256_vectors() {
+        assert_eq!(
+            b"b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7".to_vec(),
+            hex::encode(&hmac::<Sha256>(&[0x0b; 20], b"Hi There"))
+        );
+        assert_eq!(
+            b"5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843".to_vec(),
+            hex::encode(&hmac::<Sha256>(b"Jefe", b"what do ya want for nothing?"))
+        );
+        assert_eq!(
+            b"60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54".to_vec(),
+            hex::encode(&hmac::<Sha256>(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            ))
+        );
+    }
+
+    #[test]
+    fn rfc_2104_md5_vector() {
+        assert_eq!(
+            b"9294727a3638bb1c13f48ef8158bfc9d".to_vec(),
+            hex::encode(&hmac::<Md5>(&[0x0b; 16], b"Hi There"))
+        );
+    }
+
+    #[test]
+    fn streaming_matches_one_shot() {
+        let mut streaming = Hmac::<Sha1>::new(b"key");
+        streaming.update(b"The quick brown fox ");
+        streaming.update(b"jumps over the lazy dog");
+
+        let mac = hmac::<Sha1>(b"key", b"The quick brown fox jumps over the lazy dog");
+        assert_eq!(mac, streaming.finalize());
+        assert!(verify_hmac::<Sha1>(
+            b"key",
+            b"The quick brown fox jumps over the lazy dog",
+            &mac
+        ));
+        assert!(!verify_hmac::<Sha1>(
+            b"yek",
+            b"The quick brown fox jumps over the lazy dog",
+            &mac
+        ));
+    }
+}
@@ -0,0 +1,167 @@
+use super::hash::Hash;
+use super::hmac::{hmac, Hmac};
+
+pub fn hkdf_extract<H: Hash>(salt: &[u8], input_key: &[u8]) -> Vec<u8> {
+    if salt.is_empty() {
+        return hmac::<H>(&vec![0; H::OUTPUT_SIZE], input_key);
+    }
+
+    hmac::<H>(salt, input_key)
+}
+
+pub fn hkdf_expand<H: Hash>(pseudorandom_key: &[u8], info: &[u8], length: usize) -> Vec<u8> {
+    if length > 255 * H::OUTPUT_SIZE {
+        panic!("HKDF can't expand to more than 255 hash outputs.");
+    }
+
+    let mut output = Vec::with_capacity(length);
+    let mut block = vec![];
+    let mut counter = 1u8;
+
+    while output.len() < length {
+        let mut hmac = Hmac::<H>::new(pseudorandom_key);
+        hmac.update(&block);
+        hmac.update(info);
+        hmac.update(&[counter]);
+        block = hmac.finalize();
+
+        output.extend_from_slice(&block);
+        counter = counter.wrapping_add(1);
+    }
+    output.truncate(length);
+
+    output
+}
+
+pub fn hkdf<H: Hash>(salt: &[u8], input_key: &[u8], info: &[u8], length: usize) -> Vec<u8> {
+    hkdf_expand::<H>(&hkdf_extract::<H>(salt, input_key), info, length)
+}
+
+pub fn pbkdf2<H: Hash>(password: &[u8], salt: &[u8], iterations: u32, length: usize) -> Vec<u8> {
+    if iterations == 0 {
+        panic!("PBKDF2 needs at least one iteration.");
+    }
+
+    let block_count = length.div_ceil(H::OUTPUT_SIZE);
+
+    let mut output: Vec<u8> = (1..=block_count as u32)
+        .flat_map(|index| {
+            let mut u = hmac::<H>(password, &[salt, &index.to_be_bytes()].concat());
+            let mut block = u.clone();
+
+            for _ in 1..iterations {
+                u = hmac::<H>(password, &u);
+                block.iter_mut().zip(u.iter()).for_each(|(b, u)| *b ^= u);
+            }
+
+            block
+        })
+        .collect();
+    output.truncate(length);
+
+    output
+}
+
+pub fn derive_aes_128_key<H: Hash>(password: &[u8], salt: &[u8], iterations: u32) -> Vec<u8> {
+    pbkdf2::<H>(password, salt, iterations, 16)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hex;
+    use crate::sha1::Sha1;
+    use crate::sha256::Sha256;
+
+    mod hkdf {
+        use super::*;
+
+        #[test]
+        fn rfc_5869_sha256_basic() {
+            let input_key = [0x0b; 22];
+            let salt = hex::decode(b"000102030405060708090a0b0c");
+            let info = hex::decode(b"f0f1f2f3f4f5f6f7f8f9");
+
+            let prk = hkdf_extract::<Sha256>(&salt, &input_key);
+            assert_eq!(
+                b"077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5".to_vec(),
+                hex::encode(&prk)
+            );
+            assert_eq!(
+                b"3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865".to_vec(),
+                hex::encode(&hkdf_expand::<Sha256>(&prk, &info, 42))
+            );
+        }
+
+        #[test]
+        fn rfc_5869_sha256_empty_salt_and_info() {
+            assert_eq!(
+                b"8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d9d201395faa4b61a96c8".to_vec(),
+                hex::encode(&hkdf::<Sha256>(b"", &[0x0b; 22], b"", 42))
+            );
+        }
+
+        #[test]
+        fn rfc_5869_sha1_basic() {
+            let salt = hex::decode(b"000102030405060708090a0b0c");
+            let info = hex::decode(b"f0f1f2f3f4f5f6f7f8f9");
+
+            assert_eq!(
+                b"085a01ea1b10f36933068b56efa5ad81a4f14b822f5b091568a9cdd4f155fda2c22e422478d305f3f896".to_vec(),
+                hex::encode(&hkdf::<Sha1>(&salt, &[0x0b; 11], &info, 42))
+            );
+        }
+    }
+
+    mod pbkdf2 {
+        use super::*;
+
+        #[test]
+        fn rfc_6070_vectors() {
+            assert_eq!(
+                b"0c60c80f961f0e71f3a9b524af6012062fe037a6".to_vec(),
+                hex::encode(&pbkdf2::<Sha1>(b"password", b"salt", 1, 20))
+            );
+            assert_eq!(
+                b"ea6c014dc72d6f8ccd1ed92ace1d41f0d8de8957".to_vec(),
+                hex::encode(&pbkdf2::<Sha1>(b"password", b"salt", 2, 20))
+            );
+            assert_eq!(
+                b"4b007901b765489abead49d926f721d065a429c1".to_vec(),
+                hex::encode(&pbkdf2::<Sha1>(b"password", b"salt", 4096, 20))
+            );
+            assert_eq!(
+                b"3d2eec4fe41c849b80c8d83662c0e44a8b291a964cf2f07038".to_vec(),
+                hex::encode(&pbkdf2::<Sha1>(
+                    b"passwordPASSWORDpassword",
+                    b"saltSALTsaltSALTsaltSALTsaltSALTsalt",
+                    4096,
+                    25
+                ))
+            );
+        }
+
+        #[test]
+        fn sha256() {
+            assert_eq!(
+                b"ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43".to_vec(),
+                hex::encode(&pbkdf2::<Sha256>(b"password", b"salt", 2, 32))
+            );
+        }
+
+        #[test]
+        fn _derive_aes_128_key() {
+            use crate::cipher::{cbc_mode_decrypt, cbc_mode_encrypt};
+
+            let data = b"Figuring to decrypt cbc mode encryption with a derived key!!";
+            let key = derive_aes_128_key::<Sha256>(b"YELLOW SUBMARINE", b"salt", 1000);
+            let iv = vec![0; 16];
+
+            let secret = cbc_mode_encrypt(data, &key, &iv);
+            let message = cbc_mode_decrypt(&secret, &key, &iv);
+
+            assert_eq!(16, key.len());
+            assert_eq!(data.to_vec(), message[..data.len()].to_vec())
+        }
+    }
+}
@@ -0,0 +1,335 @@
+use super::blocks::pkcs7_pad;
+use super::cipher::{cbc_encrypt, Aes128, BlockCipher};
+use super::ct::ct_eq;
+use super::hash::Hash;
+use super::xor::fixed_xor;
+use std::ops::Range;
+
+pub fn secret_prefix_mac<H: Hash>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    H::digest(&[key, message].concat())
+}
+
+pub fn verify_secret_prefix_mac<H: Hash>(key: &[u8], message: &[u8], mac: &[u8]) -> bool {
+    ct_eq(&secret_prefix_mac::<H>(key, message), mac)
+}
+
+pub fn forge_length_extension<H: Hash>(
+    mac: &[u8],
+    message: &[u8],
+    suffix: &[u8],
+    key_len: usize,
+) -> Option<(Vec<u8>, Vec<u8>)> {
+    let glue = H::padding((key_len + message.len()) as u64);
+    let forged_message = [message, &glue, suffix].concat();
+
+    let mut hash = H::from_digest(mac, (key_len + message.len() + glue.len()) as u64)?;
+    hash.update(suffix);
+
+    Some((forged_message, hash.finalize()))
+}
+
+pub fn length_extension<H, F>(
+    mac: &[u8],
+    message: &[u8],
+    suffix: &[u8],
+    key_lengths: Range<usize>,
+    verify: F,
+) -> Option<(Vec<u8>, Vec<u8>)>
+where
+    H: Hash,
+    F: Fn(&[u8], &[u8]) -> bool,
+{
+    key_lengths
+        .filter_map(|key_len| forge_length_extension::<H>(mac, message, suffix, key_len))
+        .find(|(forged_message, forged_mac)| verify(forged_message, forged_mac))
+}
+
+// The last block of the CBC encryption of the PKCS#7 padded message.
+pub fn cbc_mac<C: BlockCipher>(key: &[u8], iv: &[u8], message: &[u8]) -> Vec<u8> {
+    let ciphertext = cbc_encrypt(&C::new(key), iv, &pkcs7_pad(C::BLOCK_SIZE, message));
+
+    ciphertext[ciphertext.len() - C::BLOCK_SIZE..].to_vec()
+}
+
+pub fn verify_cbc_mac<C: BlockCipher>(key: &[u8], iv: &[u8], message: &[u8], mac: &[u8]) -> bool {
+    ct_eq(&cbc_mac::<C>(key, iv, message), mac)
+}
+
+// When the verifier takes the IV from the sender, any change to the first block is cancelled
+// by flipping the same bits in the IV. `forged` may only differ from `message` in the first
+// block.
+pub fn forge_cbc_mac_iv(iv: &[u8], message: &[u8], forged: &[u8]) -> Option<Vec<u8>> {
+    let block_size = iv.len();
+    let (message, forged) = (
+        pkcs7_pad(block_size, message),
+        pkcs7_pad(block_size, forged),
+    );
+    if message.len() != forged.len() || message[block_size..] != forged[block_size..] {
+        return None;
+    }
+
+    let delta = fixed_xor(&message[..block_size], &forged[..block_size]);
+    Some(fixed_xor(iv, &delta))
+}
+
+// Under a fixed IV, the MAC of `message` is the chaining value going into whatever follows
+// its padding. XORing the first block of `suffix` with that MAC and the IV restarts the chain
+// as if `suffix` had been MACed on its own, so the result carries `suffix`'s MAC. The first
+// block of `suffix` comes out garbled, and it has to be at least a block long.
+pub fn forge_cbc_mac_extension(
+    iv: &[u8],
+    message: &[u8],
+    mac: &[u8],
+    suffix: &[u8],
+) -> Option<Vec<u8>> {
+    let block_size = iv.len();
+    if suffix.len() < block_size || mac.len() != block_size {
+        return None;
+    }
+
+    let glue = fixed_xor(&fixed_xor(&suffix[..block_size], iv), mac);
+    Some(
+        [
+            &pkcs7_pad(block_size, message),
+            &glue,
+            &suffix[block_size..],
+        ]
+        .concat(),
+    )
+}
+
+pub const CBC_MAC_HASH_KEY: &[u8] = b"YELLOW SUBMARINE";
+
+// CBC-MAC with a published key and a zero IV, pressed into service as a hash function.
+pub fn cbc_mac_hash(message: &[u8]) -> Vec<u8> {
+    cbc_mac::<Aes128>(CBC_MAC_HASH_KEY, &[0; 16], message)
+}
+
+// With the key known, the whole chain can be run backwards. The returned message is the
+// padded `prefix` followed by one block chosen so the chain lands on `target` after the final
+// block of padding.
+pub fn cbc_mac_collision<C: BlockCipher>(
+    key: &[u8],
+    iv: &[u8],
+    prefix: &[u8],
+    target: &[u8],
+) -> Option<Vec<u8>> {
+    if iv.len() != C::BLOCK_SIZE || target.len() != C::BLOCK_SIZE {
+        return None;
+    }
+
+    let cipher = C::new(key);
+    let padded = pkcs7_pad(C::BLOCK_SIZE, prefix);
+    let ciphertext = cbc_encrypt(&cipher, iv, &padded);
+    let state = &ciphertext[ciphertext.len() - C::BLOCK_SIZE..];
+
+    let padding = vec![C::BLOCK_SIZE as u8; C::BLOCK_SIZE];
+    let before_padding = fixed_xor(&cipher.decrypt_block(target), &padding);
+    let block = fixed_xor(&cipher.decrypt_block(&before_padding), state);
+
+    Some([padded, block].concat())
+}
+
+// The crafted block is whatever the cipher says it is, so formats that care about the bytes
+// after `prefix` need some slack. Appends `filler` to `prefix`, up to sixteen blocks of it,
+// until `accept` likes the collision.
+pub fn cbc_mac_collision_where<C, F>(
+    key: &[u8],
+    iv: &[u8],
+    prefix: &[u8],
+    target: &[u8],
+    filler: u8,
+    accept: F,
+) -> Option<Vec<u8>>
+where
+    C: BlockCipher,
+    F: Fn(&[u8]) -> bool,
+{
+    (0..=C::BLOCK_SIZE * 16)
+        .map(|extra| [prefix, &vec![filler; extra]].concat())
+        .filter_map(|prefix| cbc_mac_collision::<C>(key, iv, &prefix, target))
+        .find(|forged| accept(forged))
+}
+
+// JavaScript ends a `//` comment at CR, LF and the UTF-8 encoded U+2028 and U+2029, so a
+// collision hidden in a comment must avoid all of them.
+pub fn ends_line_comment(bytes: &[u8]) -> bool {
+    bytes.iter().any(|&b| b == b'\n' || b == b'\r')
+        || bytes
+            .windows(3)
+            .any(|w| w == b"\xe2\x80\xa8" || w == b"\xe2\x80\xa9")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::md4::Md4;
+    use crate::sha1::Sha1;
+    use crate::sha256::Sha224;
+
+    const MESSAGE: &[u8] =
+        b"comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+
+    #[test]
+    fn detects_tampering() {
+        let key = b"YELLOW SUBMARINE";
+        let mac = secret_prefix_mac::<Sha1>(key, MESSAGE);
+
+        assert!(verify_secret_prefix_mac::<Sha1>(key, MESSAGE, &mac));
+        assert!(!verify_secret_prefix_mac::<Sha1>(
+            key,
+            b"comment1=cooking",
+            &mac
+        ));
+        assert!(!verify_secret_prefix_mac::<Sha1>(
+            b"ORANGE SUBMARINE",
+            MESSAGE,
+            &mac
+        ));
+    }
+
+    #[test]
+    fn forges_with_known_key_length() {
+        let key = b"YELLOW SUBMARINE";
+        let mac = secret_prefix_mac::<Md4>(key, MESSAGE);
+
+        let (forged_message, forged_mac) =
+            forge_length_extension::<Md4>(&mac, MESSAGE, b";admin=true", key.len()).unwrap();
+
+        assert!(forged_message.ends_with(b";admin=true"));
+        assert_eq!(secret_prefix_mac::<Md4>(key, &forged_message), forged_mac);
+    }
+
+    #[test]
+    fn _length_extension() {
+        let key = b"Terminator X: Bring the noise";
+        let mac = secret_prefix_mac::<Sha1>(key, MESSAGE);
+        let verify =
+            |message: &[u8], mac: &[u8]| verify_secret_prefix_mac::<Sha1>(key, message, mac);
+
+        let forged = length_extension::<Sha1, _>(&mac, MESSAGE, b";admin=true", 0..64, verify);
+        assert!(forged.is_some());
+
+        let forged = length_extension::<Sha1, _>(&mac, MESSAGE, b";admin=true", 0..16, verify);
+        assert!(forged.is_none());
+    }
+
+    #[test]
+    fn truncated_digest_cannot_be_extended() {
+        let key = b"YELLOW SUBMARINE";
+        let mac = secret_prefix_mac::<Sha224>(key, MESSAGE);
+        let verify =
+            |message: &[u8], mac: &[u8]| verify_secret_prefix_mac::<Sha224>(key, message, mac);
+
+        assert_eq!(
+            None,
+            forge_length_extension::<Sha224>(&mac, MESSAGE, b";admin=true", key.len())
+        );
+        assert_eq!(
+            None,
+            length_extension::<Sha224, _>(&mac, MESSAGE, b";admin=true", 0..64, verify)
+        );
+    }
+
+    #[test]
+    fn cbc_mac_is_the_last_cbc_block() {
+        use openssl::symm::{encrypt, Cipher};
+
+        let key = b"YELLOW SUBMARINE";
+        let iv = [0u8; 16];
+        let ciphertext = encrypt(Cipher::aes_128_cbc(), key, Some(&iv), MESSAGE).unwrap();
+        let mac = cbc_mac::<Aes128>(key, &iv, MESSAGE);
+
+        assert_eq!(ciphertext[ciphertext.len() - 16..].to_vec(), mac);
+        assert!(verify_cbc_mac::<Aes128>(key, &iv, MESSAGE, &mac));
+        assert!(!verify_cbc_mac::<Aes128>(key, &[1; 16], MESSAGE, &mac));
+    }
+
+    #[test]
+    fn iv_absorbs_first_block_changes() {
+        let key = b"YELLOW SUBMARINE";
+        let iv = [7u8; 16];
+        let mac = cbc_mac::<Aes128>(key, &iv, MESSAGE);
+        let forged = [b"COMMENT1=COOKING", &MESSAGE[16..]].concat();
+
+        let forged_iv = forge_cbc_mac_iv(&iv, MESSAGE, &forged).unwrap();
+        assert!(verify_cbc_mac::<Aes128>(key, &forged_iv, &forged, &mac));
+
+        let too_far = [&MESSAGE[..16], b"X", &MESSAGE[17..]].concat();
+        assert_eq!(None, forge_cbc_mac_iv(&iv, MESSAGE, &too_far));
+        assert_eq!(None, forge_cbc_mac_iv(&iv, MESSAGE, &MESSAGE[1..]));
+    }
+
+    #[test]
+    fn fixed_iv_mac_extends() {
+        let key = b"YELLOW SUBMARINE";
+        let iv = [0u8; 16];
+        let mac = cbc_mac::<Aes128>(key, &iv, MESSAGE);
+        let suffix = b"0123456789abcdef;admin=true";
+        let suffix_mac = cbc_mac::<Aes128>(key, &iv, suffix);
+
+        let forged = forge_cbc_mac_extension(&iv, MESSAGE, &mac, suffix).unwrap();
+        assert!(forged.starts_with(MESSAGE));
+        assert!(forged.ends_with(b";admin=true"));
+        assert!(verify_cbc_mac::<Aes128>(key, &iv, &forged, &suffix_mac));
+        assert_eq!(
+            None,
+            forge_cbc_mac_extension(&iv, MESSAGE, &mac, b";admin=true")
+        );
+    }
+
+    #[test]
+    fn line_terminators() {
+        assert!(ends_line_comment(b"abc\ndef"));
+        assert!(ends_line_comment(b"abc\r"));
+        assert!(ends_line_comment("a\u{2028}b".as_bytes()));
+        assert!(ends_line_comment("a\u{2029}b".as_bytes()));
+        assert!(!ends_line_comment(b"\xe2\x80\xa7\x10\x10"));
+    }
+
+    #[test]
+    fn collides_with_any_target() {
+        let iv = [3u8; 16];
+        let target = cbc_mac::<Aes128>(CBC_MAC_HASH_KEY, &iv, MESSAGE);
+
+        let forged =
+            cbc_mac_collision::<Aes128>(CBC_MAC_HASH_KEY, &iv, b";admin=true", &target).unwrap();
+        assert!(forged.starts_with(b";admin=true"));
+        assert_eq!(32, forged.len());
+        assert_eq!(target, cbc_mac::<Aes128>(CBC_MAC_HASH_KEY, &iv, &forged));
+        assert_eq!(
+            None,
+            cbc_mac_collision::<Aes128>(CBC_MAC_HASH_KEY, &iv, MESSAGE, &target[1..])
+        );
+    }
+
+    #[test]
+    fn collision_filler_satisfies_predicate() {
+        let target = cbc_mac_hash(MESSAGE);
+        let accept = |forged: &[u8]| forged[11..].iter().all(u8::is_ascii_alphanumeric);
+
+        let forged = cbc_mac_collision_where::<Aes128, _>(
+            CBC_MAC_HASH_KEY,
+            &[0; 16],
+            b"",
+            &target,
+            b'x',
+            accept,
+        );
+        assert_eq!(None, forged);
+
+        let accept = |forged: &[u8]| !forged[3..].contains(&b'\n');
+        let forged = cbc_mac_collision_where::<Aes128, _>(
+            CBC_MAC_HASH_KEY,
+            &[0; 16],
+            b"abc",
+            &target,
+            b' ',
+            accept,
+        )
+        .unwrap();
+        assert!(forged.starts_with(b"abc"));
+        assert!(accept(&forged));
+        assert_eq!(target, cbc_mac_hash(&forged));
+    }
+}
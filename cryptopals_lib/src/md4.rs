@@ -0,0 +1,122 @@
+use super::hash::{self, Compression, Endian, MerkleDamgard};
+
+pub struct Md4Compression;
+
+pub type Md4 = MerkleDamgard<Md4Compression>;
+
+impl Compression for Md4Compression {
+    type State = [u32; 4];
+
+    const INITIAL_STATE: Self::State = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476];
+    const ENDIAN: Endian = Endian::Little;
+    const OUTPUT_WORDS: usize = 4;
+
+    fn compress(state: &mut Self::State, block: &[u8]) {
+        let x = Self::ENDIAN.read_block(block);
+
+        let f = |x: u32, y: u32, z: u32| (x & y) | (!x & z);
+        let g = |x: u32, y: u32, z: u32| (x & y) | (x & z) | (y & z);
+        let h = |x: u32, y: u32, z: u32| x ^ y ^ z;
+
+        let [mut a, mut b, mut c, mut d] = *state;
+
+        // Each step updates `a`, then the registers rotate so the next step targets `d`.
+        for i in 0..16 {
+            let shift = [3, 7, 11, 19][i % 4];
+            let result = a
+                .wrapping_add(f(b, c, d))
+                .wrapping_add(x[i])
+                .rotate_left(shift);
+            a = d;
+            d = c;
+            c = b;
+            b = result;
+        }
+
+        for i in 0..16 {
+            let k = [0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14, 3, 7, 11, 15][i];
+            let shift = [3, 5, 9, 13][i % 4];
+            let result = a
+                .wrapping_add(g(b, c, d))
+                .wrapping_add(x[k])
+                .wrapping_add(0x5A827999)
+                .rotate_left(shift);
+            a = d;
+            d = c;
+            c = b;
+            b = result;
+        }
+
+        for i in 0..16 {
+            let k = [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15][i];
+            let shift = [3, 9, 11, 15][i % 4];
+            let result = a
+                .wrapping_add(h(b, c, d))
+                .wrapping_add(x[k])
+                .wrapping_add(0x6ED9EBA1)
+                .rotate_left(shift);
+            a = d;
+            d = c;
+            c = b;
+            b = result;
+        }
+
+        for (word, value) in state.iter_mut().zip([a, b, c, d].iter()) {
+            *word = word.wrapping_add(*value);
+        }
+    }
+}
+
+pub fn digest(data: &[u8]) -> Vec<u8> {
+    let mut md4 = Md4::new();
+    md4.update(data);
+    md4.finalize()
+}
+
+pub fn md_padding(length: u64) -> Vec<u8> {
+    hash::md_padding::<Md4Compression>(length)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hex;
+
+    #[test]
+    fn rfc_1320_vectors() {
+        let vectors: Vec<(&[u8], &[u8])> = vec![
+            (b"", b"31d6cfe0d16ae931b73c59d7e0c089c0"),
+            (b"a", b"bde52cb31de33e46245e05fbdbd6fb24"),
+            (b"abc", b"a448017aaf21d8525fc10ae87aa6729d"),
+            (b"message digest", b"d9130a8164549fe818874806e1c7014b"),
+            (
+                b"abcdefghijklmnopqrstuvwxyz",
+                b"d79e1c308aa5bbcdeea8ed63df412da9",
+            ),
+            (
+                b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+                b"043f8582f241db351ce627e153e7f0e4",
+            ),
+            (
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                b"e33b4ddc9c38f2199c3e7b164fcc0536",
+            ),
+        ];
+
+        for (message, expected) in vectors {
+            assert_eq!(expected.to_vec(), hex::encode(&digest(message)));
+        }
+    }
+
+    #[test]
+    fn streaming_matches_one_shot() {
+        let data = b"The quick brown fox jumps over the lazy dog, again and again and again!";
+
+        for chunk_size in 1..data.len() {
+            let mut md4 = Md4::new();
+            data.chunks(chunk_size).for_each(|chunk| md4.update(chunk));
+
+            assert_eq!(digest(data), md4.finalize());
+        }
+    }
+}
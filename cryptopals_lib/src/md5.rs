@@ -0,0 +1,103 @@
+use super::hash::{self, Compression, Endian, MerkleDamgard};
+
+pub struct Md5Compression;
+
+pub type Md5 = MerkleDamgard<Md5Compression>;
+
+impl Compression for Md5Compression {
+    type State = [u32; 4];
+
+    const INITIAL_STATE: Self::State = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476];
+    const ENDIAN: Endian = Endian::Little;
+    const OUTPUT_WORDS: usize = 4;
+
+    fn compress(state: &mut Self::State, block: &[u8]) {
+        let m = Self::ENDIAN.read_block(block);
+        let [mut a, mut b, mut c, mut d] = *state;
+
+        for i in 0..64 {
+            let (f, g, shift) = match i / 16 {
+                0 => ((b & c) | (!b & d), i, [7, 12, 17, 22][i % 4]),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16, [5, 9, 14, 20][i % 4]),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16, [4, 11, 16, 23][i % 4]),
+                _ => (c ^ (b | !d), (7 * i) % 16, [6, 10, 15, 21][i % 4]),
+            };
+
+            let f = f.wrapping_add(a).wrapping_add(K[i]).wrapping_add(m[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(shift));
+        }
+
+        for (word, value) in state.iter_mut().zip([a, b, c, d].iter()) {
+            *word = word.wrapping_add(*value);
+        }
+    }
+}
+
+#[rustfmt::skip]
+const K: [u32; 64] = [
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501,
+    0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821,
+    0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+    0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed, 0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a,
+    0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c, 0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70,
+    0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+    0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+    0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+
+pub fn digest(data: &[u8]) -> Vec<u8> {
+    let mut md5 = Md5::new();
+    md5.update(data);
+    md5.finalize()
+}
+
+pub fn md_padding(length: u64) -> Vec<u8> {
+    hash::md_padding::<Md5Compression>(length)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hex;
+
+    #[test]
+    fn rfc_1321_vectors() {
+        let vectors: Vec<(&[u8], &[u8])> = vec![
+            (b"", b"d41d8cd98f00b204e9800998ecf8427e"),
+            (b"a", b"0cc175b9c0f1b6a831c399e269772661"),
+            (b"abc", b"900150983cd24fb0d6963f7d28e17f72"),
+            (b"message digest", b"f96b697d7cb7938d525a2f31aaf161d0"),
+            (
+                b"abcdefghijklmnopqrstuvwxyz",
+                b"c3fcd3d76192e4007dfb496cca67e13b",
+            ),
+            (
+                b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+                b"d174ab98d277d9f5a5611c2c9f419d9f",
+            ),
+            (
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+                b"57edf4a22be3c955ac49da2e2107b67a",
+            ),
+        ];
+
+        for (message, expected) in vectors {
+            assert_eq!(expected.to_vec(), hex::encode(&digest(message)));
+        }
+    }
+
+    #[test]
+    fn streaming_matches_one_shot() {
+        let data = b"The quick brown fox jumps over the lazy dog, again and again and again!";
+
+        for chunk_size in 1..data.len() {
+            let mut md5 = Md5::new();
+            data.chunks(chunk_size).for_each(|chunk| md5.update(chunk));
+
+            assert_eq!(digest(data), md5.finalize());
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::VecDeque;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    ToServer,
+    ToClient,
+}
+
+impl Direction {
+    pub fn reverse(self) -> Self {
+        match self {
+            Direction::ToServer => Direction::ToClient,
+            Direction::ToClient => Direction::ToServer,
+        }
+    }
+}
+
+// A protocol participant as a state machine: every message it receives can produce replies.
+pub trait Party<M> {
+    // Messages sent before anything has been received, usually only the client has any.
+    fn start(&mut self) -> Vec<M> {
+        Vec::new()
+    }
+
+    fn receive(&mut self, message: M) -> Vec<M>;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action<M> {
+    Forward,
+    Drop,
+    Replace(M),
+    // Delivers the original message, followed by `M` to the same recipient.
+    Inject(M),
+}
+
+// Sits on the wire and decides what happens to every message in flight.
+pub trait Interceptor<M> {
+    fn intercept(&mut self, direction: Direction, message: &M) -> Action<M>;
+}
+
+pub struct Passthrough;
+
+impl<M> Interceptor<M> for Passthrough {
+    fn intercept(&mut self, _direction: Direction, _message: &M) -> Action<M> {
+        Action::Forward
+    }
+}
+
+impl<M, F> Interceptor<M> for F
+where
+    F: FnMut(Direction, &M) -> Action<M>,
+{
+    fn intercept(&mut self, direction: Direction, message: &M) -> Action<M> {
+        self(direction, message)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<M> {
+    Sent(Direction, M),
+    Delivered(Direction, M),
+    Dropped(Direction, M),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transcript<M> {
+    pub events: Vec<Event<M>>,
+}
+
+impl<M> Default for Transcript<M> {
+    fn default() -> Self {
+        Self { events: Vec::new() }
+    }
+}
+
+impl<M> Transcript<M> {
+    // What each side actually received, which differs from what was sent under attack.
+    pub fn delivered(&self, direction: Direction) -> Vec<&M> {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Delivered(to, message) if *to == direction => Some(message),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn sent(&self, direction: Direction) -> Vec<&M> {
+        self.events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Sent(to, message) if *to == direction => Some(message),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+// Runs the protocol in memory until nobody has anything left to say, or `max_messages`
+// have been delivered so a misbehaving protocol can't loop forever.
+pub fn run<M, C, S, I>(
+    client: &mut C,
+    server: &mut S,
+    interceptor: &mut I,
+    max_messages: usize,
+) -> Transcript<M>
+where
+    M: Clone,
+    C: Party<M>,
+    S: Party<M>,
+    I: Interceptor<M>,
+{
+    let mut transcript = Transcript::default();
+    let mut in_flight: VecDeque<(Direction, M)> = client
+        .start()
+        .into_iter()
+        .map(|message| (Direction::ToServer, message))
+        .chain(
+            server
+                .start()
+                .into_iter()
+                .map(|message| (Direction::ToClient, message)),
+        )
+        .collect();
+    let mut delivered = 0;
+
+    while let Some((direction, message)) = in_flight.pop_front() {
+        if delivered >= max_messages {
+            break;
+        }
+        transcript
+            .events
+            .push(Event::Sent(direction, message.clone()));
+
+        let arriving = match interceptor.intercept(direction, &message) {
+            Action::Forward => vec![message],
+            Action::Drop => {
+                transcript.events.push(Event::Dropped(direction, message));
+                vec![]
+            }
+            Action::Replace(replacement) => vec![replacement],
+            Action::Inject(extra) => vec![message, extra],
+        };
+
+        for message in arriving {
+            // An injected message counts too, so the pair can't step over the limit. Whatever
+            // gets cut off still shows up in the transcript.
+            if delivered >= max_messages {
+                transcript.events.push(Event::Dropped(direction, message));
+                continue;
+            }
+            transcript
+                .events
+                .push(Event::Delivered(direction, message.clone()));
+            delivered += 1;
+
+            let replies = match direction {
+                Direction::ToServer => server.receive(message),
+                Direction::ToClient => client.receive(message),
+            };
+            in_flight.extend(
+                replies
+                    .into_iter()
+                    .map(|reply| (direction.reverse(), reply)),
+            );
+        }
+    }
+
+    transcript
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Counts down from its starting number, bouncing each value to the other side.
+    struct Counter {
+        start: Option<u32>,
+        seen: Vec<u32>,
+    }
+
+    impl Counter {
+        fn new(start: Option<u32>) -> Self {
+            Self {
+                start,
+                seen: Vec::new(),
+            }
+        }
+    }
+
+    impl Party<u32> for Counter {
+        fn start(&mut self) -> Vec<u32> {
+            self.start.into_iter().collect()
+        }
+
+        fn receive(&mut self, message: u32) -> Vec<u32> {
+            self.seen.push(message);
+            match message {
+                0 => vec![],
+                n => vec![n - 1],
+            }
+        }
+    }
+
+    #[test]
+    fn passthrough_delivers_everything() {
+        let (mut client, mut server) = (Counter::new(Some(4)), Counter::new(None));
+
+        let transcript = run(&mut client, &mut server, &mut Passthrough, 100);
+
+        assert_eq!(vec![4, 2, 0], server.seen);
+        assert_eq!(vec![3, 1], client.seen);
+        assert_eq!(vec![&4, &2, &0], transcript.delivered(Direction::ToServer));
+    }
+
+    #[test]
+    fn interceptor_can_rewrite_traffic() {
+        let (mut client, mut server) = (Counter::new(Some(4)), Counter::new(None));
+        let mut mitm = |direction: Direction, message: &u32| match (direction, *message) {
+            (Direction::ToServer, 4) => Action::Replace(2),
+            (Direction::ToClient, 1) => Action::Inject(7),
+            (_, 6) => Action::Drop,
+            _ => Action::Forward,
+        };
+
+        let transcript = run(&mut client, &mut server, &mut mitm, 100);
+
+        assert_eq!(vec![2, 0], server.seen);
+        assert_eq!(vec![1, 7], client.seen);
+        assert_eq!(vec![&4, &0, &6], transcript.sent(Direction::ToServer));
+        assert!(transcript
+            .events
+            .contains(&Event::Dropped(Direction::ToServer, 6)));
+    }
+
+    #[test]
+    fn stops_after_max_messages() {
+        let (mut client, mut server) = (Counter::new(Some(1000)), Counter::new(None));
+
+        run(&mut client, &mut server, &mut Passthrough, 10);
+
+        assert_eq!(5, server.seen.len());
+        assert_eq!(5, client.seen.len());
+    }
+
+    #[test]
+    fn injections_respect_max_messages() {
+        let (mut client, mut server) = (Counter::new(Some(1000)), Counter::new(None));
+        let mut mitm = |_direction: Direction, _message: &u32| Action::Inject(7);
+
+        let transcript = run(&mut client, &mut server, &mut mitm, 9);
+
+        assert_eq!(9, server.seen.len() + client.seen.len());
+        assert_eq!(
+            9,
+            transcript
+                .events
+                .iter()
+                .filter(|event| matches!(event, Event::Delivered(..)))
+                .count()
+        );
+        assert!(matches!(
+            transcript.events.last(),
+            Some(Event::Dropped(_, 7))
+        ));
+    }
+}
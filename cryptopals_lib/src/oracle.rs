@@ -1,4 +1,45 @@
-use crate::cipher::{cbc_mode_encrypt, ecb_mode_encrypt};
+use crate::cipher::{
+    cbc_mode_decrypt, cbc_mode_encrypt, ctr_mode_decrypt, ctr_mode_edit, ctr_mode_encrypt,
+    ecb_mode_encrypt, EncryptionMode,
+};
+
+use crate::bignum::{self, BigUint};
+use crate::deflate::compress;
+use crate::hash::Hash;
+use crate::mac::{secret_prefix_mac, verify_secret_prefix_mac};
+use crate::pkcs1::PaddingCheck;
+use crate::rsa::{KeyPair, PublicKey, RsaError};
+use crate::sha256::Sha256;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum OracleError {
+    HighAscii(Vec<u8>),
+    Replayed,
+    Rsa(RsaError),
+}
+
+impl fmt::Display for OracleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OracleError::HighAscii(plaintext) => {
+                write!(f, "Invalid ASCII in plaintext: {:?}", plaintext)
+            }
+            OracleError::Replayed => write!(f, "Ciphertext was already decrypted"),
+            OracleError::Rsa(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl From<RsaError> for OracleError {
+    fn from(error: RsaError) -> Self {
+        OracleError::Rsa(error)
+    }
+}
+
+impl std::error::Error for OracleError {}
 
 pub fn encryption_oracle(data: &[u8]) -> Vec<u8> {
     use rand::rngs::ThreadRng;
@@ -78,6 +119,247 @@ pub fn ecb_encryption_oracle_generator(secret_text: &[u8]) -> impl Fn(&[u8]) ->
     }
 }
 
+#[allow(clippy::type_complexity)]
+pub fn ctr_edit_oracle_generator(
+    secret_text: &[u8],
+) -> (Vec<u8>, impl Fn(&[u8], usize, &[u8]) -> Vec<u8>) {
+    use rand::Rng;
+
+    let mut rng = rand::thread_rng();
+    let key: Vec<u8> = (0..16).map(|_| rng.gen::<u8>()).collect();
+    let nonce = rng.gen::<u64>();
+
+    let ciphertext = ctr_mode_encrypt(secret_text, &key, nonce);
+    let edit = move |ciphertext: &[u8], offset: usize, newtext: &[u8]| {
+        ctr_mode_edit(ciphertext, &key, nonce, offset, newtext)
+    };
+
+    (ciphertext, edit)
+}
+
+pub const COMMENT_PREFIX: &[u8] = b"comment1=cooking%20MCs;userdata=";
+pub const COMMENT_SUFFIX: &[u8] = b";comment2=%20like%20a%20pound%20of%20bacon";
+
+pub fn quote_out(data: &[u8]) -> Vec<u8> {
+    data.iter()
+        .flat_map(|byte| match byte {
+            b';' => b"%3B".to_vec(),
+            b'=' => b"%3D".to_vec(),
+            _ => vec![*byte],
+        })
+        .collect()
+}
+
+pub fn wrap_userdata(userdata: &[u8]) -> Vec<u8> {
+    [COMMENT_PREFIX, &quote_out(userdata), COMMENT_SUFFIX].concat()
+}
+
+pub fn parse_key_values(data: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+    data.split(|byte| *byte == b';')
+        .filter_map(|pair| {
+            let split_at = pair.iter().position(|byte| *byte == b'=')?;
+            Some((pair[..split_at].to_vec(), pair[split_at + 1..].to_vec()))
+        })
+        .collect()
+}
+
+pub fn is_admin(data: &[u8]) -> bool {
+    parse_key_values(data)
+        .iter()
+        .any(|(key, value)| key == b"admin" && value == b"true")
+}
+
+#[allow(clippy::type_complexity)]
+pub fn ctr_comment_oracle_generator() -> (impl Fn(&[u8]) -> Vec<u8>, impl Fn(&[u8]) -> bool) {
+    use rand::Rng;
+
+    let mut rng = rand::thread_rng();
+    let key: Vec<u8> = (0..16).map(|_| rng.gen::<u8>()).collect();
+    let nonce = rng.gen::<u64>();
+
+    let encrypt_key = key.clone();
+    let encrypt =
+        move |userdata: &[u8]| ctr_mode_encrypt(&wrap_userdata(userdata), &encrypt_key, nonce);
+    let check_admin = move |ciphertext: &[u8]| is_admin(&ctr_mode_decrypt(ciphertext, &key, nonce));
+
+    (encrypt, check_admin)
+}
+
+pub fn validate_ascii(plaintext: Vec<u8>) -> Result<Vec<u8>, OracleError> {
+    if plaintext.iter().any(|byte| *byte > 127) {
+        return Err(OracleError::HighAscii(plaintext));
+    }
+
+    Ok(plaintext)
+}
+
+#[allow(clippy::type_complexity)]
+pub fn cbc_key_as_iv_oracle_generator() -> (
+    impl Fn(&[u8]) -> Vec<u8>,
+    impl Fn(&[u8]) -> Result<Vec<u8>, OracleError>,
+) {
+    use rand::Rng;
+
+    let mut rng = rand::thread_rng();
+    let key: Vec<u8> = (0..16).map(|_| rng.gen::<u8>()).collect();
+
+    let encrypt_key = key.clone();
+    let encrypt = move |userdata: &[u8]| {
+        cbc_mode_encrypt(&wrap_userdata(userdata), &encrypt_key, &encrypt_key)
+    };
+    let decrypt = move |ciphertext: &[u8]| validate_ascii(cbc_mode_decrypt(ciphertext, &key, &key));
+
+    (encrypt, decrypt)
+}
+
+#[allow(clippy::type_complexity)]
+pub fn secret_prefix_mac_oracle_generator<H: Hash>(
+) -> (impl Fn(&[u8]) -> Vec<u8>, impl Fn(&[u8], &[u8]) -> bool) {
+    use rand::Rng;
+
+    let mut rng = rand::thread_rng();
+    let key_len = rng.gen_range(1, 33);
+    let key: Vec<u8> = (0..key_len).map(|_| rng.gen::<u8>()).collect();
+
+    let sign_key = key.clone();
+    let sign = move |message: &[u8]| secret_prefix_mac::<H>(&sign_key, message);
+    let verify =
+        move |message: &[u8], mac: &[u8]| verify_secret_prefix_mac::<H>(&key, message, mac);
+
+    (sign, verify)
+}
+
+pub const SESSION_ID: &[u8] = b"TmV2ZXIgcmV2ZWFsIHRoZSBXdS1UYW5nIFNlY3JldCE=";
+
+pub fn format_request(session_id: &[u8], payload: &[u8]) -> Vec<u8> {
+    [
+        b"POST / HTTP/1.1\nHost: hapless.com\nCookie: sessionid=",
+        session_id,
+        format!("\nContent-Length: {}\n", payload.len()).as_bytes(),
+        payload,
+    ]
+    .concat()
+}
+
+// Compresses the request, encrypts it under a fresh key and only tells how long it came out.
+// ECB leaks far more than the length, so only the CTR and CBC modes from the challenge are
+// offered.
+pub fn compression_oracle_generator(
+    session_id: &[u8],
+    mode: EncryptionMode,
+) -> impl Fn(&[u8]) -> usize {
+    use rand::Rng;
+
+    assert!(
+        mode != EncryptionMode::ECB,
+        "Compression oracle only supports CTR or CBC"
+    );
+    let session_id = session_id.to_owned();
+
+    move |payload| {
+        let mut rng = rand::thread_rng();
+        let key: Vec<u8> = (0..16).map(|_| rng.gen::<u8>()).collect();
+        let compressed = compress(&format_request(&session_id, payload));
+
+        match mode {
+            EncryptionMode::ECB => unreachable!(),
+            EncryptionMode::CBC => {
+                let iv: Vec<u8> = (0..16).map(|_| rng.gen::<u8>()).collect();
+                cbc_mode_encrypt(&compressed, &key, &iv).len()
+            }
+            EncryptionMode::CTR => ctr_mode_encrypt(&compressed, &key, rng.gen()).len(),
+        }
+    }
+}
+
+// Decrypts any RSA ciphertext, but only once. Seen ciphertexts are remembered by their
+// SHA-256 hash.
+pub struct DecryptOnceServer {
+    key_pair: KeyPair,
+    seen: HashSet<Vec<u8>>,
+}
+
+impl DecryptOnceServer {
+    pub fn new(key_pair: KeyPair) -> Self {
+        Self {
+            key_pair,
+            seen: HashSet::new(),
+        }
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.key_pair.public
+    }
+
+    pub fn decrypt(&mut self, ciphertext: &BigUint) -> Result<BigUint, OracleError> {
+        let fingerprint = Sha256::digest(&bignum::to_bytes_be(ciphertext));
+        if self.seen.contains(&fingerprint) {
+            return Err(OracleError::Replayed);
+        }
+
+        let plaintext = self.key_pair.private.decrypt(ciphertext)?;
+        self.seen.insert(fingerprint);
+
+        Ok(plaintext)
+    }
+}
+
+// Decrypts anything but only reveals the low `bits` bits of the plaintext, the parity when
+// `bits` is 1.
+pub struct ParityServer {
+    key_pair: KeyPair,
+    bits: u32,
+}
+
+impl ParityServer {
+    pub fn new(key_pair: KeyPair) -> Self {
+        Self::with_bits(key_pair, 1)
+    }
+
+    pub fn with_bits(key_pair: KeyPair, bits: u32) -> Self {
+        assert!(bits > 0 && bits <= 32, "Oracle leaks 1 to 32 bits");
+        Self { key_pair, bits }
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.key_pair.public
+    }
+
+    pub fn low_bits(&self, ciphertext: &BigUint) -> Result<u64, OracleError> {
+        let plaintext = self.key_pair.private.decrypt(ciphertext)?;
+        let mask = BigUint::from((1u64 << self.bits) - 1);
+
+        Ok(u64::try_from(plaintext & mask).expect("Masked to at most 32 bits"))
+    }
+
+    pub fn is_even(&self, ciphertext: &BigUint) -> Result<bool, OracleError> {
+        Ok(self.low_bits(ciphertext)? & 1 == 0)
+    }
+}
+
+// Decrypts anything and says whether the block passes `check`, nothing more.
+pub struct PaddingServer {
+    key_pair: KeyPair,
+    check: PaddingCheck,
+}
+
+impl PaddingServer {
+    pub fn new(key_pair: KeyPair, check: PaddingCheck) -> Self {
+        Self { key_pair, check }
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.key_pair.public
+    }
+
+    pub fn is_conforming(&self, ciphertext: &BigUint) -> Result<bool, OracleError> {
+        let plaintext = self.key_pair.private.decrypt(ciphertext)?;
+        let block = bignum::to_bytes_be_padded(&plaintext, self.key_pair.public.size());
+
+        Ok(self.check.conforms(&block))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -100,4 +382,152 @@ mod test {
             );
         }
     }
+
+    mod decrypt_once {
+        use super::*;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        #[test]
+        fn rejects_replays() {
+            let key_pair = KeyPair::generate(128, 3, &mut StdRng::seed_from_u64(41)).unwrap();
+            let mut server = DecryptOnceServer::new(key_pair);
+            let ciphertext = server.public_key().encrypt_bytes(b"once").unwrap();
+            let n = server.public_key().n.clone();
+
+            assert_eq!(
+                Ok(bignum::from_bytes_be(b"once")),
+                server.decrypt(&ciphertext)
+            );
+            assert_eq!(Err(OracleError::Replayed), server.decrypt(&ciphertext));
+            assert_eq!(
+                Err(OracleError::Rsa(RsaError::MessageTooLarge)),
+                server.decrypt(&n)
+            );
+        }
+    }
+
+    mod parity {
+        use super::*;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        #[test]
+        fn leaks_only_low_bits() {
+            let key_pair = KeyPair::generate(128, 3, &mut StdRng::seed_from_u64(46)).unwrap();
+            let parity = ParityServer::new(key_pair.clone());
+            let nibble = ParityServer::with_bits(key_pair, 4);
+            let even = parity
+                .public_key()
+                .encrypt(&BigUint::from(0xcafeu32))
+                .unwrap();
+            let odd = parity
+                .public_key()
+                .encrypt(&BigUint::from(0xbeefu32))
+                .unwrap();
+
+            assert_eq!(Ok(true), parity.is_even(&even));
+            assert_eq!(Ok(false), parity.is_even(&odd));
+            assert_eq!(Ok(1), parity.low_bits(&odd));
+            assert_eq!(Ok(0xf), nibble.low_bits(&odd));
+        }
+    }
+
+    mod padding {
+        use super::*;
+        use crate::pkcs1;
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        #[test]
+        fn checks_only_what_it_is_told() {
+            let mut rng = StdRng::seed_from_u64(47);
+            let key_pair = KeyPair::generate(256, 3, &mut rng).unwrap();
+            let prefix = PaddingServer::new(key_pair.clone(), PaddingCheck::Prefix);
+            let structure = PaddingServer::new(key_pair, PaddingCheck::Structure);
+
+            let padded = pkcs1::encrypt(prefix.public_key(), b"kick it, CC", &mut rng).unwrap();
+            let mut block = vec![0x00, 0x02];
+            block.resize(32, 0xff);
+            let unterminated = prefix.public_key().encrypt_bytes(&block).unwrap();
+
+            assert_eq!(Ok(true), prefix.is_conforming(&padded));
+            assert_eq!(Ok(true), structure.is_conforming(&padded));
+            assert_eq!(Ok(true), prefix.is_conforming(&unterminated));
+            assert_eq!(Ok(false), structure.is_conforming(&unterminated));
+        }
+    }
+
+    mod comment {
+        use super::*;
+
+        #[test]
+        fn quotes_out_metacharacters() {
+            let wrapped = wrap_userdata(b";admin=true;");
+
+            assert_eq!(
+                b"comment1=cooking%20MCs;userdata=%3Badmin%3Dtrue%3B;comment2=%20like%20a%20pound%20of%20bacon".to_vec(),
+                wrapped
+            );
+            assert!(!is_admin(&wrapped));
+        }
+
+        #[test]
+        fn parses_key_values() {
+            let pairs = parse_key_values(b"comment1=cooking%20MCs;admin=true;junk");
+
+            assert_eq!(
+                vec![
+                    (b"comment1".to_vec(), b"cooking%20MCs".to_vec()),
+                    (b"admin".to_vec(), b"true".to_vec()),
+                ],
+                pairs
+            );
+            assert!(is_admin(b"comment1=cooking%20MCs;admin=true;junk"));
+        }
+    }
+
+    mod compression {
+        use super::*;
+
+        #[test]
+        fn formats_request() {
+            assert_eq!(
+                b"POST / HTTP/1.1\nHost: hapless.com\nCookie: sessionid=abc\nContent-Length: 5\nhello"
+                    .to_vec(),
+                format_request(b"abc", b"hello")
+            );
+        }
+
+        #[test]
+        fn leaks_compressed_length() {
+            let compressed = compress(&format_request(SESSION_ID, b"hello")).len();
+
+            let ctr = compression_oracle_generator(SESSION_ID, EncryptionMode::CTR);
+            assert_eq!(compressed, ctr(b"hello"));
+
+            let cbc = compression_oracle_generator(SESSION_ID, EncryptionMode::CBC);
+            assert_eq!(compressed / 16 * 16 + 16, cbc(b"hello"));
+            assert!(ctr(b"sessionid=TmV2") < ctr(b"sessionid=XyZw"));
+        }
+
+        #[test]
+        #[should_panic(expected = "Compression oracle only supports CTR or CBC")]
+        fn rejects_ecb() {
+            let _ = compression_oracle_generator(SESSION_ID, EncryptionMode::ECB);
+        }
+    }
+
+    mod ascii {
+        use super::*;
+
+        #[test]
+        fn rejects_high_ascii() {
+            assert_eq!(Ok(b"hello".to_vec()), validate_ascii(b"hello".to_vec()));
+            assert_eq!(
+                Err(OracleError::HighAscii(vec![b'h', 200])),
+                validate_ascii(vec![b'h', 200])
+            );
+        }
+    }
 }
@@ -0,0 +1,591 @@
+use super::bignum::{self, BigUint};
+use super::ct::ct_eq;
+use super::hash::Hash;
+use super::rsa::{PrivateKey, PublicKey, RsaError};
+use super::sha1::Sha1;
+use super::sha256::Sha256;
+use rand::Rng;
+use std::convert::TryFrom;
+
+// DER encoded DigestInfo up to and including the OCTET STRING header, from RFC 8017.
+const SHA1_PREFIX: [u8; 15] = [
+    0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00, 0x04, 0x14,
+];
+const SHA256_PREFIX: [u8; 19] = [
+    0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05,
+    0x00, 0x04, 0x20,
+];
+
+// Bytes of 0xff a well formed signature block carries at minimum.
+const MIN_PADDING: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DigestAlgorithm {
+    Sha1,
+    Sha256,
+}
+
+impl DigestAlgorithm {
+    pub fn prefix(self) -> &'static [u8] {
+        match self {
+            DigestAlgorithm::Sha1 => &SHA1_PREFIX,
+            DigestAlgorithm::Sha256 => &SHA256_PREFIX,
+        }
+    }
+
+    pub fn hash(self, message: &[u8]) -> Vec<u8> {
+        match self {
+            DigestAlgorithm::Sha1 => Sha1::digest(message),
+            DigestAlgorithm::Sha256 => Sha256::digest(message),
+        }
+    }
+
+    pub fn digest_info(self, message: &[u8]) -> Vec<u8> {
+        [self.prefix(), &self.hash(message)].concat()
+    }
+}
+
+// EMSA-PKCS1-v1_5: 00 01 ff .. ff 00 DigestInfo, `length` bytes in total.
+pub fn encode(
+    algorithm: DigestAlgorithm,
+    message: &[u8],
+    length: usize,
+) -> Result<Vec<u8>, RsaError> {
+    let digest_info = algorithm.digest_info(message);
+    if length < digest_info.len() + MIN_PADDING + 3 {
+        return Err(RsaError::MessageTooLarge);
+    }
+
+    let padding = vec![0xff; length - digest_info.len() - 3];
+    Ok([&[0x00, 0x01], padding.as_slice(), &[0x00], &digest_info].concat())
+}
+
+pub fn sign(
+    private: &PrivateKey,
+    algorithm: DigestAlgorithm,
+    message: &[u8],
+) -> Result<Vec<u8>, RsaError> {
+    let length = bignum::bits(&private.n).div_ceil(8) as usize;
+    let block = encode(algorithm, message, length)?;
+    let signature = private.decrypt(&bignum::from_bytes_be(&block))?;
+
+    Ok(bignum::to_bytes_be_padded(&signature, length))
+}
+
+// Recovers the signature block, left padded back to the modulus length.
+fn open(public: &PublicKey, signature: &[u8]) -> Option<Vec<u8>> {
+    if signature.len() != public.size() {
+        return None;
+    }
+
+    let block = public.encrypt(&bignum::from_bytes_be(signature)).ok()?;
+    Some(bignum::to_bytes_be_padded(&block, public.size()))
+}
+
+// Re-encodes the expected block and compares it in full.
+pub fn verify(
+    public: &PublicKey,
+    algorithm: DigestAlgorithm,
+    message: &[u8],
+    signature: &[u8],
+) -> bool {
+    let block = match open(public, signature) {
+        Some(block) => block,
+        None => return false,
+    };
+
+    match encode(algorithm, message, public.size()) {
+        Ok(expected) => ct_eq(&expected, &block),
+        Err(_) => false,
+    }
+}
+
+// DON'T USE: parses the block left to right and stops reading after the hash, so whatever
+// follows it is never looked at.
+pub fn verify_broken(
+    public: &PublicKey,
+    algorithm: DigestAlgorithm,
+    message: &[u8],
+    signature: &[u8],
+) -> bool {
+    let block = match open(public, signature) {
+        Some(block) => block,
+        None => return false,
+    };
+
+    if block.len() < 3 || block[..3] != [0x00, 0x01, 0xff] {
+        return false;
+    }
+    let rest = &block[2..];
+    let padding = rest.iter().take_while(|&&b| b == 0xff).count();
+    let rest = &rest[padding..];
+
+    let digest_info = algorithm.digest_info(message);
+    match rest.split_first() {
+        Some((0x00, rest)) => rest.starts_with(&digest_info),
+        _ => false,
+    }
+}
+
+// Bleichenbacher's e = 3 forgery: a block that starts 00 01 ff 00 DigestInfo followed by
+// garbage, picked so that it's a perfect e-th power. Needs roughly two thirds of the block to
+// be garbage, so small keys with long hashes won't fit.
+pub fn forge_signature(
+    public: &PublicKey,
+    algorithm: DigestAlgorithm,
+    message: &[u8],
+) -> Option<Vec<u8>> {
+    let e = u32::try_from(&public.e).ok()?;
+    let length = public.size();
+    let prefix = [
+        &[0x00, 0x01, 0xff, 0x00],
+        algorithm.digest_info(message).as_slice(),
+    ]
+    .concat();
+    if prefix.len() >= length {
+        return None;
+    }
+
+    let garbage = length - prefix.len();
+    let lower = bignum::from_bytes_be(&[prefix.as_slice(), &vec![0x00; garbage]].concat());
+    let upper = bignum::from_bytes_be(&[prefix.as_slice(), &vec![0xff; garbage]].concat());
+
+    let root = bignum::iroot(&upper, e);
+    if root.pow(e) < lower {
+        return None;
+    }
+
+    Some(bignum::to_bytes_be_padded(&root, length))
+}
+
+// EME-PKCS1-v1_5: 00 02 PS 00 M, with PS at least eight random non-zero bytes.
+pub fn pad<R: Rng>(message: &[u8], length: usize, rng: &mut R) -> Result<Vec<u8>, RsaError> {
+    if length < message.len() + MIN_PADDING + 3 {
+        return Err(RsaError::MessageTooLarge);
+    }
+
+    let padding: Vec<u8> = (0..length - message.len() - 3)
+        .map(|_| rng.gen_range(1, 256) as u8)
+        .collect();
+    Ok([&[0x00, 0x02], padding.as_slice(), &[0x00], message].concat())
+}
+
+pub fn unpad(block: &[u8]) -> Option<Vec<u8>> {
+    if block.len() < MIN_PADDING + 3 || block[..2] != [0x00, 0x02] {
+        return None;
+    }
+
+    let separator = block[2..].iter().position(|&b| b == 0x00)? + 2;
+    if separator < MIN_PADDING + 2 {
+        return None;
+    }
+
+    Some(block[separator + 1..].to_vec())
+}
+
+pub fn encrypt<R: Rng>(
+    public: &PublicKey,
+    message: &[u8],
+    rng: &mut R,
+) -> Result<BigUint, RsaError> {
+    public.encrypt_bytes(&pad(message, public.size(), rng)?)
+}
+
+pub fn decrypt(private: &PrivateKey, ciphertext: &BigUint) -> Result<Vec<u8>, RsaError> {
+    let length = bignum::bits(&private.n).div_ceil(8) as usize;
+    let block = bignum::to_bytes_be_padded(&private.decrypt(ciphertext)?, length);
+
+    unpad(&block).ok_or(RsaError::InvalidPadding)
+}
+
+// What a padding oracle checks before it calls a block conforming. The looser the check, the
+// more often the attack learns something from a query.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PaddingCheck {
+    // Only the leading 00 02, as in challenges 47 and 48.
+    Prefix,
+    // The full structure `unpad` accepts.
+    Structure,
+    // The full structure and a message of exactly this many bytes.
+    Length(usize),
+}
+
+impl PaddingCheck {
+    pub fn conforms(self, block: &[u8]) -> bool {
+        match self {
+            PaddingCheck::Prefix => block.len() >= 2 && block[..2] == [0x00, 0x02],
+            PaddingCheck::Structure => unpad(block).is_some(),
+            PaddingCheck::Length(length) => unpad(block).is_some_and(|m| m.len() == length),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recovery {
+    // The whole padded block, left padded to the modulus length.
+    pub block: Vec<u8>,
+    pub queries: usize,
+}
+
+// Bleichenbacher '98. Every conforming c s^e says 2B <= m s mod n < 3B, which narrows down
+// the set of intervals m can lie in until a single value is left. Needs an `oracle` that
+// answers for any ciphertext, and gives up as soon as it doesn't.
+pub fn padding_oracle_attack<F>(
+    ciphertext: &BigUint,
+    public: &PublicKey,
+    mut oracle: F,
+) -> Option<Recovery>
+where
+    F: FnMut(&BigUint) -> Option<bool>,
+{
+    let n = &public.n;
+    let length = public.size();
+    if length < MIN_PADDING + 3 {
+        return None;
+    }
+
+    let mut queries = 0;
+    let mut conforming = |s: &BigUint, c0: &BigUint| {
+        queries += 1;
+        oracle(&(bignum::modpow(s, &public.e, n) * c0 % n))
+    };
+
+    let one = BigUint::from(1u32);
+    let b = one.clone() << (8 * (length - 2));
+    let (b2, b3) = (&b * 2u32, &b * 3u32);
+
+    // Step 1: blinding. A ciphertext that already conforms is used as is.
+    let mut s0 = one.clone();
+    while !conforming(&s0, ciphertext)? {
+        s0 += 1u32;
+    }
+    let c0 = bignum::modpow(&s0, &public.e, n) * ciphertext % n;
+    let mut intervals = vec![(b2.clone(), &b3 - 1u32)];
+    let mut s = BigUint::from(0u32);
+
+    loop {
+        // Step 2: find the next s that makes c0 s^e conform.
+        if s == BigUint::from(0u32) {
+            // 2.a: start at n / 3B, the smallest s that can move m out of [2B, 3B).
+            s = div_ceil(n, &b3);
+            while !conforming(&s, &c0)? {
+                s += 1u32;
+            }
+        } else if intervals.len() > 1 {
+            // 2.b: several intervals left, step through s one at a time.
+            s += 1u32;
+            while !conforming(&s, &c0)? {
+                s += 1u32;
+            }
+        } else {
+            // 2.c: one interval [a, b] left, so search s in ranges that roughly halve it.
+            let (lower, upper) = &intervals[0];
+            let mut r = div_ceil(&((upper * &s - &b2) * 2u32), n);
+            'search: loop {
+                let rn = &r * n;
+                s = div_ceil(&(&b2 + &rn), upper);
+                while &s * lower < &b3 + &rn {
+                    if conforming(&s, &c0)? {
+                        break 'search;
+                    }
+                    s += 1u32;
+                }
+                r += 1u32;
+            }
+        }
+
+        // Step 3: keep the parts of each interval where m s - r n falls in [2B, 3B).
+        let mut narrowed = Vec::new();
+        for (lower, upper) in intervals.iter() {
+            let low_product = lower * &s + 1u32;
+            let mut r = if low_product > b3 {
+                div_ceil(&(low_product - &b3), n)
+            } else {
+                BigUint::from(0u32)
+            };
+            let r_max = (upper * &s - &b2) / n;
+
+            while r <= r_max {
+                let rn = &r * n;
+                let new_lower = lower.max(&div_ceil(&(&b2 + &rn), &s)).clone();
+                let new_upper = upper.min(&((&b3 - 1u32 + &rn) / &s)).clone();
+                if new_lower <= new_upper {
+                    narrowed.push((new_lower, new_upper));
+                }
+                r += 1u32;
+            }
+        }
+        if narrowed.is_empty() {
+            return None;
+        }
+        intervals = merge_intervals(narrowed);
+
+        // Step 4: a single value left is m s0 mod n.
+        if intervals.len() == 1 && intervals[0].0 == intervals[0].1 {
+            let m = &intervals[0].0 * bignum::modinv(&s0, n)? % n;
+            return Some(Recovery {
+                block: bignum::to_bytes_be_padded(&m, length),
+                queries,
+            });
+        }
+    }
+}
+
+fn div_ceil(a: &BigUint, b: &BigUint) -> BigUint {
+    (a + b - 1u32) / b
+}
+
+// Sorts intervals and merges the ones that overlap.
+fn merge_intervals(mut intervals: Vec<(BigUint, BigUint)>) -> Vec<(BigUint, BigUint)> {
+    intervals.sort();
+
+    let mut merged: Vec<(BigUint, BigUint)> = Vec::with_capacity(intervals.len());
+    for (lower, upper) in intervals {
+        match merged.last_mut() {
+            Some((_, last_upper)) if lower <= *last_upper => {
+                if upper > *last_upper {
+                    *last_upper = upper;
+                }
+            }
+            _ => merged.push((lower, upper)),
+        }
+    }
+
+    merged
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rsa::KeyPair;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn key_pair(bits: u64, e: u32) -> KeyPair {
+        KeyPair::generate(bits, e, &mut StdRng::seed_from_u64(bits)).unwrap()
+    }
+
+    mod encoding {
+        use super::*;
+
+        #[test]
+        fn digest_info_lengths() {
+            assert_eq!(35, DigestAlgorithm::Sha1.digest_info(b"").len());
+            assert_eq!(51, DigestAlgorithm::Sha256.digest_info(b"").len());
+        }
+
+        #[test]
+        fn block_layout() {
+            let block = encode(DigestAlgorithm::Sha1, b"hi mom", 64).unwrap();
+
+            assert_eq!(64, block.len());
+            assert_eq!([0x00, 0x01], block[..2]);
+            assert!(block[2..28].iter().all(|&b| b == 0xff));
+            assert_eq!(0x00, block[28]);
+            assert_eq!(DigestAlgorithm::Sha1.digest_info(b"hi mom"), block[29..]);
+            assert_eq!(
+                Err(RsaError::MessageTooLarge),
+                encode(DigestAlgorithm::Sha256, b"hi mom", 61)
+            );
+        }
+    }
+
+    mod verification {
+        use super::*;
+
+        #[test]
+        fn both_verifiers_accept_real_signatures() {
+            let key_pair = key_pair(512, 3);
+
+            for algorithm in [DigestAlgorithm::Sha1, DigestAlgorithm::Sha256].iter() {
+                let signature = sign(&key_pair.private, *algorithm, b"hi mom").unwrap();
+
+                assert!(verify(&key_pair.public, *algorithm, b"hi mom", &signature));
+                assert!(verify_broken(
+                    &key_pair.public,
+                    *algorithm,
+                    b"hi mom",
+                    &signature
+                ));
+                assert!(!verify(&key_pair.public, *algorithm, b"hi dad", &signature));
+                assert!(!verify_broken(
+                    &key_pair.public,
+                    *algorithm,
+                    b"hi dad",
+                    &signature
+                ));
+            }
+        }
+
+        #[test]
+        fn rejects_wrong_algorithm_and_length() {
+            let key_pair = key_pair(512, 3);
+            let signature = sign(&key_pair.private, DigestAlgorithm::Sha1, b"hi mom").unwrap();
+
+            assert!(!verify(
+                &key_pair.public,
+                DigestAlgorithm::Sha256,
+                b"hi mom",
+                &signature
+            ));
+            assert!(!verify(
+                &key_pair.public,
+                DigestAlgorithm::Sha1,
+                b"hi mom",
+                &signature[1..]
+            ));
+        }
+    }
+
+    mod forgery {
+        use super::*;
+        use crate::bignum::BigUint;
+
+        // Forging only involves the public key, and with e = 3 the forged block never wraps
+        // around the modulus, so any odd modulus of the right size will do.
+        fn public_key(bits: usize) -> PublicKey {
+            PublicKey {
+                n: (BigUint::from(1u32) << bits) - 1u32,
+                e: BigUint::from(3u32),
+            }
+        }
+
+        #[test]
+        fn only_the_broken_verifier_accepts_forgeries() {
+            for (bits, algorithm) in [
+                (1024, DigestAlgorithm::Sha1),
+                (1536, DigestAlgorithm::Sha256),
+            ]
+            .iter()
+            {
+                let public = public_key(*bits);
+                let forged = forge_signature(&public, *algorithm, b"hi mom").unwrap();
+
+                assert!(verify_broken(&public, *algorithm, b"hi mom", &forged));
+                assert!(!verify(&public, *algorithm, b"hi mom", &forged));
+                assert!(!verify_broken(&public, *algorithm, b"hi dad", &forged));
+            }
+        }
+
+        #[test]
+        fn small_keys_leave_no_room() {
+            let public = public_key(1024);
+
+            assert_eq!(
+                None,
+                forge_signature(&public, DigestAlgorithm::Sha256, b"hi mom")
+            );
+        }
+    }
+
+    mod encryption {
+        use super::*;
+
+        #[test]
+        fn padding_round_trip() {
+            let mut rng = StdRng::seed_from_u64(47);
+            let block = pad(b"kick it, CC", 32, &mut rng).unwrap();
+
+            assert_eq!(32, block.len());
+            assert_eq!([0x00, 0x02], block[..2]);
+            assert!(block[2..20].iter().all(|&b| b != 0x00));
+            assert_eq!(0x00, block[20]);
+            assert_eq!(Some(b"kick it, CC".to_vec()), unpad(&block));
+            assert_eq!(
+                Err(RsaError::MessageTooLarge),
+                pad(b"kick it, CC", 21, &mut rng)
+            );
+        }
+
+        #[test]
+        fn unpad_rejects_malformed_blocks() {
+            let short_padding = [&[0x00, 0x02], &[0xff; 7][..], &[0x00], b"hi"].concat();
+            let unterminated = [&[0x00, 0x02], &[0xff; 12][..]].concat();
+            let signature_type = [&[0x00, 0x01], &[0xff; 8][..], &[0x00], b"hi"].concat();
+
+            assert_eq!(None, unpad(&short_padding));
+            assert_eq!(None, unpad(&unterminated));
+            assert_eq!(None, unpad(&signature_type));
+            assert!(PaddingCheck::Prefix.conforms(&unterminated));
+            assert!(!PaddingCheck::Structure.conforms(&unterminated));
+        }
+
+        #[test]
+        fn encrypt_and_decrypt() {
+            let mut rng = StdRng::seed_from_u64(47);
+            let key_pair = key_pair(256, 3);
+            let ciphertext = encrypt(&key_pair.public, b"kick it, CC", &mut rng).unwrap();
+            let raw = key_pair.public.encrypt_bytes(b"kick it, CC").unwrap();
+
+            assert_eq!(
+                Ok(b"kick it, CC".to_vec()),
+                decrypt(&key_pair.private, &ciphertext)
+            );
+            assert_eq!(
+                Err(RsaError::InvalidPadding),
+                decrypt(&key_pair.private, &raw)
+            );
+        }
+
+        #[test]
+        fn merges_overlapping_intervals() {
+            let big = |n: u32| BigUint::from(n);
+            let intervals = vec![
+                (big(5), big(9)),
+                (big(1), big(3)),
+                (big(3), big(4)),
+                (big(8), big(12)),
+            ];
+
+            assert_eq!(
+                vec![(big(1), big(4)), (big(5), big(12))],
+                merge_intervals(intervals)
+            );
+        }
+    }
+
+    mod padding_oracle {
+        use super::*;
+
+        fn recover(bits: u64, check: PaddingCheck) -> Recovery {
+            let mut rng = StdRng::seed_from_u64(2);
+            let key_pair = key_pair(bits, 3);
+            let ciphertext = encrypt(&key_pair.public, b"kick it, CC", &mut rng).unwrap();
+
+            padding_oracle_attack(&ciphertext, &key_pair.public, |c| {
+                let block = key_pair.private.decrypt(c).ok()?;
+                Some(check.conforms(&bignum::to_bytes_be_padded(&block, key_pair.public.size())))
+            })
+            .unwrap()
+        }
+
+        #[test]
+        fn recovers_plaintext() {
+            let recovery = recover(256, PaddingCheck::Prefix);
+
+            assert_eq!(Some(b"kick it, CC".to_vec()), unpad(&recovery.block));
+            assert!(recovery.queries > 256);
+        }
+
+        #[test]
+        fn check_decides_what_the_oracle_sees() {
+            let block = [&[0x00, 0x02], &[0xff; 9][..], &[0x00], b"kick it, CC"].concat();
+
+            assert!(PaddingCheck::Prefix.conforms(&block));
+            assert!(PaddingCheck::Structure.conforms(&block));
+            assert!(PaddingCheck::Length(11).conforms(&block));
+            assert!(!PaddingCheck::Length(12).conforms(&block));
+        }
+
+        #[test]
+        fn gives_up_when_the_oracle_does() {
+            let key_pair = key_pair(256, 3);
+            let ciphertext = key_pair.public.encrypt_bytes(b"hi").unwrap();
+
+            assert_eq!(
+                None,
+                padding_oracle_attack(&ciphertext, &key_pair.public, |_| None)
+            );
+        }
+    }
+}
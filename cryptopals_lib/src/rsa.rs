@@ -0,0 +1,630 @@
+use super::bignum::{self, BigUint, RandBigInt};
+use rand::Rng;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+pub const DEFAULT_EXPONENT: u32 = 65537;
+// Below this there are too few primes with their top two bits set to find a distinct pair
+// that works with e, and under six bits `generate_prime` can't make them at all.
+pub const MIN_MODULUS_BITS: u64 = 16;
+
+#[derive(Debug, PartialEq)]
+pub enum RsaError {
+    // e shares a factor with p - 1 or q - 1, so it has no inverse.
+    InvalidExponent,
+    MessageTooLarge,
+    MalformedKey,
+    InvalidPadding,
+    KeyTooSmall,
+}
+
+impl fmt::Display for RsaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RsaError::InvalidExponent => write!(f, "Exponent isn't invertible mod phi(n)"),
+            RsaError::MessageTooLarge => write!(f, "Message must be smaller than the modulus"),
+            RsaError::MalformedKey => write!(f, "Key isn't in the expected hex format"),
+            RsaError::InvalidPadding => write!(f, "Plaintext isn't PKCS#1 v1.5 padded"),
+            RsaError::KeyTooSmall => {
+                write!(f, "Modulus must be at least {} bits long", MIN_MODULUS_BITS)
+            }
+        }
+    }
+}
+
+impl Error for RsaError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PublicKey {
+    pub n: BigUint,
+    pub e: BigUint,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrivateKey {
+    pub n: BigUint,
+    pub d: BigUint,
+    pub p: BigUint,
+    pub q: BigUint,
+    // CRT parameters: d mod (p - 1), d mod (q - 1) and q^-1 mod p.
+    dp: BigUint,
+    dq: BigUint,
+    q_inv: BigUint,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyPair {
+    pub public: PublicKey,
+    pub private: PrivateKey,
+}
+
+impl KeyPair {
+    // Draws fresh primes until e is invertible, so only pathological exponents (even ones)
+    // fail.
+    pub fn generate<R: Rng>(bits: u64, e: u32, rng: &mut R) -> Result<Self, RsaError> {
+        if bits < MIN_MODULUS_BITS {
+            return Err(RsaError::KeyTooSmall);
+        }
+        if e < 3 || e.is_multiple_of(2) {
+            return Err(RsaError::InvalidExponent);
+        }
+        let e = BigUint::from(e);
+
+        loop {
+            let p = bignum::generate_prime(bits - bits / 2, rng);
+            let q = bignum::generate_prime(bits / 2, rng);
+            if p == q {
+                continue;
+            }
+
+            if let Ok(key_pair) = Self::from_primes(p, q, e.clone()) {
+                return Ok(key_pair);
+            }
+        }
+    }
+
+    pub fn from_primes(p: BigUint, q: BigUint, e: BigUint) -> Result<Self, RsaError> {
+        let n = &p * &q;
+        let phi = (&p - 1u32) * (&q - 1u32);
+        let d = bignum::modinv(&e, &phi).ok_or(RsaError::InvalidExponent)?;
+        let private = PrivateKey::new(p, q, d)?;
+
+        Ok(Self {
+            public: PublicKey { n, e },
+            private,
+        })
+    }
+}
+
+impl PublicKey {
+    pub fn size(&self) -> usize {
+        bignum::bits(&self.n).div_ceil(8) as usize
+    }
+
+    pub fn encrypt(&self, message: &BigUint) -> Result<BigUint, RsaError> {
+        if message >= &self.n {
+            return Err(RsaError::MessageTooLarge);
+        }
+
+        Ok(bignum::modpow(message, &self.e, &self.n))
+    }
+
+    pub fn encrypt_bytes(&self, message: &[u8]) -> Result<BigUint, RsaError> {
+        self.encrypt(&bignum::from_bytes_be(message))
+    }
+
+    // `n:e` with both numbers in hex.
+    pub fn to_hex(&self) -> Vec<u8> {
+        join_hex(&[&self.n, &self.e])
+    }
+
+    pub fn from_hex(hex: &[u8]) -> Result<Self, RsaError> {
+        match split_hex(hex)?.as_slice() {
+            [n, e] => Ok(Self {
+                n: n.clone(),
+                e: e.clone(),
+            }),
+            _ => Err(RsaError::MalformedKey),
+        }
+    }
+}
+
+impl PrivateKey {
+    pub fn new(p: BigUint, q: BigUint, d: BigUint) -> Result<Self, RsaError> {
+        let q_inv = bignum::modinv(&q, &p).ok_or(RsaError::MalformedKey)?;
+
+        Ok(Self {
+            n: &p * &q,
+            dp: &d % (&p - 1u32),
+            dq: &d % (&q - 1u32),
+            d,
+            p,
+            q,
+            q_inv,
+        })
+    }
+
+    // Two half size exponentiations recombined with Garner's formula.
+    pub fn decrypt(&self, ciphertext: &BigUint) -> Result<BigUint, RsaError> {
+        if ciphertext >= &self.n {
+            return Err(RsaError::MessageTooLarge);
+        }
+
+        let m1 = bignum::modpow(ciphertext, &self.dp, &self.p);
+        let m2 = bignum::modpow(ciphertext, &self.dq, &self.q);
+        let h = &self.q_inv * (&m1 + &self.p - &m2 % &self.p) % &self.p;
+
+        Ok(m2 + h * &self.q)
+    }
+
+    pub fn decrypt_without_crt(&self, ciphertext: &BigUint) -> Result<BigUint, RsaError> {
+        if ciphertext >= &self.n {
+            return Err(RsaError::MessageTooLarge);
+        }
+
+        Ok(bignum::modpow(ciphertext, &self.d, &self.n))
+    }
+
+    // Leading zero bytes of the message don't survive the round trip through an integer.
+    pub fn decrypt_bytes(&self, ciphertext: &BigUint) -> Result<Vec<u8>, RsaError> {
+        Ok(bignum::to_bytes_be(&self.decrypt(ciphertext)?))
+    }
+
+    // `p:q:d` with each number in hex, n and the CRT parameters are recomputed on import.
+    pub fn to_hex(&self) -> Vec<u8> {
+        join_hex(&[&self.p, &self.q, &self.d])
+    }
+
+    pub fn from_hex(hex: &[u8]) -> Result<Self, RsaError> {
+        match split_hex(hex)?.as_slice() {
+            [p, q, d] => Self::new(p.clone(), q.clone(), d.clone()),
+            _ => Err(RsaError::MalformedKey),
+        }
+    }
+}
+
+fn join_hex(numbers: &[&BigUint]) -> Vec<u8> {
+    numbers
+        .iter()
+        .map(|n| bignum::to_hex(n))
+        .collect::<Vec<_>>()
+        .join(&b':')
+}
+
+fn split_hex(hex: &[u8]) -> Result<Vec<BigUint>, RsaError> {
+    hex.split(|&b| b == b':')
+        .map(|part| {
+            if part.is_empty() || !part.iter().all(|b| b.is_ascii_hexdigit()) {
+                return Err(RsaError::MalformedKey);
+            }
+            Ok(bignum::from_hex(part))
+        })
+        .collect()
+}
+
+// Håstad's broadcast attack: the same message encrypted to e different public keys with
+// exponent e. CRT gives m^e mod n_0 * n_1 * ..., and as m < n_i that's m^e over the integers.
+pub fn hastad_broadcast(ciphertexts: &[(BigUint, PublicKey)]) -> Option<BigUint> {
+    let e = &ciphertexts.first()?.1.e;
+    let k = u32::try_from(e).ok()?;
+    if ciphertexts.len() < k as usize || ciphertexts.iter().any(|(_, key)| &key.e != e) {
+        return None;
+    }
+
+    let (residues, moduli): (Vec<BigUint>, Vec<BigUint>) = ciphertexts[..k as usize]
+        .iter()
+        .map(|(c, key)| (c.clone(), key.n.clone()))
+        .unzip();
+    let (power, _product) = bignum::crt(&residues, &moduli)?;
+
+    bignum::exact_root(&power, k)
+}
+
+// Blinds the ciphertext as C' = s^e * C, which the oracle hasn't seen, then divides the
+// resulting s * P by s.
+pub fn recover_unpadded_message<F, R>(
+    ciphertext: &BigUint,
+    public: &PublicKey,
+    mut decrypt: F,
+    rng: &mut R,
+) -> Option<BigUint>
+where
+    F: FnMut(&BigUint) -> Option<BigUint>,
+    R: Rng,
+{
+    let n = &public.n;
+    let (s, s_inv) = loop {
+        let s = rng.gen_biguint_range(&BigUint::from(2u32), n);
+        if let Some(s_inv) = bignum::modinv(&s, n) {
+            break (s, s_inv);
+        }
+    };
+
+    let blinded = bignum::modpow(&s, &public.e, n) * ciphertext % n;
+    let plaintext = decrypt(&blinded)?;
+
+    Some(plaintext * s_inv % n)
+}
+
+// Multiplying the ciphertext by (2^k)^e shifts the plaintext left by k bits mod n. The
+// reduction subtracts j n for some j < 2^k, and as n is odd the low k bits of the result give
+// j back. The j's are the base 2^k digits of m / n, so after enough rounds the interval
+// [a n / 2^t, (a + 1) n / 2^t) they pin down holds only m. `progress` sees the upper bound
+// after every round.
+pub fn low_bits_attack<F, P>(
+    ciphertext: &BigUint,
+    public: &PublicKey,
+    bits: u32,
+    mut oracle: F,
+    mut progress: P,
+) -> Option<BigUint>
+where
+    F: FnMut(&BigUint) -> Option<u64>,
+    P: FnMut(&BigUint),
+{
+    if bits == 0 || bits > 32 {
+        return None;
+    }
+
+    let n = &public.n;
+    let modulus = BigUint::from(1u64 << bits);
+    let n_inv = bignum::modinv(&(n % &modulus), &modulus)?;
+    let multiplier = bignum::modpow(&modulus, &public.e, n);
+
+    let mut c = ciphertext.clone();
+    let mut a = BigUint::from(0u32);
+    let mut shift = 0u64;
+    while shift < bignum::bits(n) {
+        c = c * &multiplier % n;
+        let low = BigUint::from(oracle(&c)?) % &modulus;
+        let j = (&modulus - &low * &n_inv % &modulus) % &modulus;
+
+        a = (a << bits as usize) + j;
+        shift += bits as u64;
+        progress(&(((&a + 1u32) * n) >> shift as usize));
+    }
+
+    // The interval is narrower than 1, so m is the only integer in it.
+    let product = a * n;
+    let lower = &product >> shift as usize;
+    if lower.clone() << shift as usize == product {
+        Some(lower)
+    } else {
+        Some(lower + 1u32)
+    }
+}
+
+// Challenge 46's oracle only says whether the plaintext is even.
+pub fn parity_attack<F, P>(
+    ciphertext: &BigUint,
+    public: &PublicKey,
+    mut is_even: F,
+    progress: P,
+) -> Option<BigUint>
+where
+    F: FnMut(&BigUint) -> Option<bool>,
+    P: FnMut(&BigUint),
+{
+    low_bits_attack(
+        ciphertext,
+        public,
+        1,
+        |c| is_even(c).map(|even| if even { 0 } else { 1 }),
+        progress,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn big(n: u64) -> BigUint {
+        BigUint::from(n)
+    }
+
+    fn key_pair(bits: u64, seed: u64) -> KeyPair {
+        KeyPair::generate(bits, 3, &mut StdRng::seed_from_u64(seed)).unwrap()
+    }
+
+    mod generation {
+        use super::*;
+
+        #[test]
+        fn textbook_example() {
+            let key_pair = KeyPair::from_primes(big(61), big(53), big(17)).unwrap();
+
+            assert_eq!(big(3233), key_pair.public.n);
+            assert_eq!(big(2753), key_pair.private.d);
+            assert_eq!(big(2790), key_pair.public.encrypt(&big(65)).unwrap());
+            assert_eq!(big(65), key_pair.private.decrypt(&big(2790)).unwrap());
+        }
+
+        #[test]
+        fn modulus_has_requested_size() {
+            for (seed, bits) in [64, 127, 256].iter().enumerate() {
+                let key_pair = key_pair(*bits, seed as u64);
+
+                assert_eq!(*bits, bignum::bits(&key_pair.public.n));
+                assert_eq!(big(3), key_pair.public.e);
+            }
+        }
+
+        #[test]
+        fn rejects_bad_exponents() {
+            let mut rng = StdRng::seed_from_u64(1);
+
+            assert_eq!(
+                Err(RsaError::InvalidExponent),
+                KeyPair::generate(64, 4, &mut rng)
+            );
+            assert_eq!(
+                Err(RsaError::InvalidExponent),
+                KeyPair::from_primes(big(7), big(11), big(3)).map(|_| ())
+            );
+        }
+
+        #[test]
+        fn rejects_small_moduli() {
+            let mut rng = StdRng::seed_from_u64(1);
+
+            for bits in [0, 1, 5, 15].iter() {
+                assert_eq!(
+                    Err(RsaError::KeyTooSmall),
+                    KeyPair::generate(*bits, 3, &mut rng)
+                );
+            }
+            assert_eq!(16, bignum::bits(&key_pair(16, 1).public.n));
+        }
+
+        #[test]
+        fn seeded_rng_is_reproducible() {
+            assert_eq!(key_pair(128, 7), key_pair(128, 7));
+            assert_ne!(key_pair(128, 7), key_pair(128, 8));
+        }
+    }
+
+    mod encryption {
+        use super::*;
+
+        #[test]
+        fn round_trip() {
+            let mut rng = StdRng::seed_from_u64(39);
+            let key_pair = KeyPair::generate(256, DEFAULT_EXPONENT, &mut rng).unwrap();
+            let ciphertext = key_pair.public.encrypt_bytes(b"Attack at dawn").unwrap();
+
+            assert_eq!(
+                b"Attack at dawn".to_vec(),
+                key_pair.private.decrypt_bytes(&ciphertext).unwrap()
+            );
+        }
+
+        #[test]
+        fn crt_matches_plain_decryption() {
+            let key_pair = key_pair(128, 3);
+            let mut rng = StdRng::seed_from_u64(4);
+
+            for _ in 0..20 {
+                let c = bignum::RandBigInt::gen_biguint_below(&mut rng, &key_pair.public.n);
+
+                assert_eq!(
+                    key_pair.private.decrypt_without_crt(&c),
+                    key_pair.private.decrypt(&c)
+                );
+            }
+        }
+
+        #[test]
+        fn message_must_fit_modulus() {
+            let key_pair = key_pair(64, 5);
+            let n = key_pair.public.n.clone();
+
+            assert_eq!(Err(RsaError::MessageTooLarge), key_pair.public.encrypt(&n));
+            assert_eq!(Err(RsaError::MessageTooLarge), key_pair.private.decrypt(&n));
+            assert_eq!(8, key_pair.public.size());
+        }
+    }
+
+    mod serialisation {
+        use super::*;
+
+        #[test]
+        fn hex_round_trip() {
+            let key_pair = key_pair(128, 6);
+
+            assert_eq!(
+                Ok(key_pair.public.clone()),
+                PublicKey::from_hex(&key_pair.public.to_hex())
+            );
+            assert_eq!(
+                Ok(key_pair.private.clone()),
+                PrivateKey::from_hex(&key_pair.private.to_hex())
+            );
+        }
+
+        #[test]
+        fn textbook_key_format() {
+            let key_pair = KeyPair::from_primes(big(61), big(53), big(17)).unwrap();
+
+            assert_eq!(b"0ca1:11".to_vec(), key_pair.public.to_hex());
+            assert_eq!(b"3d:35:0ac1".to_vec(), key_pair.private.to_hex());
+        }
+
+        #[test]
+        fn rejects_malformed_keys() {
+            assert_eq!(Err(RsaError::MalformedKey), PublicKey::from_hex(b"0ca1"));
+            assert_eq!(Err(RsaError::MalformedKey), PublicKey::from_hex(b"0ca1:"));
+            assert_eq!(Err(RsaError::MalformedKey), PublicKey::from_hex(b"0ca1:1g"));
+            assert_eq!(Err(RsaError::MalformedKey), PrivateKey::from_hex(b"3d:35"));
+            assert_eq!(
+                Ok(PublicKey {
+                    n: big(0xca1),
+                    e: big(17)
+                }),
+                PublicKey::from_hex(b"CA1:11")
+            );
+        }
+    }
+
+    mod broadcast {
+        use super::*;
+
+        fn broadcast(e: u32, count: usize, message: &[u8]) -> Vec<(BigUint, PublicKey)> {
+            let mut rng = StdRng::seed_from_u64(e as u64);
+
+            (0..count)
+                .map(|_| {
+                    let key = KeyPair::generate(256, e, &mut rng).unwrap().public;
+                    (key.encrypt_bytes(message).unwrap(), key)
+                })
+                .collect()
+        }
+
+        #[test]
+        fn recovers_message_for_small_exponents() {
+            let message = b"Hastad says hi".to_vec();
+
+            for e in [3, 5, 7].iter() {
+                let ciphertexts = broadcast(*e, *e as usize, &message);
+
+                assert_eq!(
+                    Some(bignum::from_bytes_be(&message)),
+                    hastad_broadcast(&ciphertexts)
+                );
+            }
+        }
+
+        #[test]
+        fn needs_e_ciphertexts() {
+            let ciphertexts = broadcast(5, 4, b"Hastad says hi");
+
+            assert_eq!(None, hastad_broadcast(&ciphertexts));
+            assert_eq!(None, hastad_broadcast(&[]));
+        }
+    }
+
+    mod unpadded_recovery {
+        use super::*;
+
+        #[test]
+        fn unblinds_oracle_output() {
+            let mut rng = StdRng::seed_from_u64(42);
+
+            for bits in [64, 256].iter() {
+                let key_pair = KeyPair::generate(*bits, DEFAULT_EXPONENT, &mut rng).unwrap();
+                let message = BigUint::from(0x5ec2e7u32);
+                let ciphertext = key_pair.public.encrypt(&message).unwrap();
+                let mut queries = Vec::new();
+
+                let recovered = recover_unpadded_message(
+                    &ciphertext,
+                    &key_pair.public,
+                    |c| {
+                        queries.push(c.clone());
+                        key_pair.private.decrypt(c).ok()
+                    },
+                    &mut rng,
+                );
+
+                assert_eq!(Some(message), recovered);
+                assert_eq!(1, queries.len());
+                assert_ne!(ciphertext, queries[0]);
+            }
+        }
+    }
+
+    mod low_bits {
+        use super::*;
+
+        fn low_bits(key_pair: &KeyPair, bits: u32, c: &BigUint) -> Option<u64> {
+            let plaintext = key_pair.private.decrypt(c).ok()?;
+            let mask = BigUint::from((1u64 << bits) - 1);
+            u64::try_from(plaintext & mask).ok()
+        }
+
+        #[test]
+        fn parity_recovers_every_byte() {
+            let key_pair = key_pair(256, 46);
+            let mut rng = StdRng::seed_from_u64(46);
+
+            for _ in 0..5 {
+                let message = rng.gen_biguint_below(&key_pair.public.n);
+                let ciphertext = key_pair.public.encrypt(&message).unwrap();
+                let mut rounds = 0;
+
+                let recovered = parity_attack(
+                    &ciphertext,
+                    &key_pair.public,
+                    |c| low_bits(&key_pair, 1, c).map(|bit| bit == 0),
+                    |upper| {
+                        rounds += 1;
+                        assert!(upper >= &message);
+                    },
+                );
+
+                assert_eq!(Some(message), recovered);
+                assert_eq!(256, rounds);
+            }
+        }
+
+        #[test]
+        fn wider_oracles_need_fewer_rounds() {
+            let key_pair = key_pair(256, 47);
+            let message = bignum::from_bytes_be(b"Funky Cold Medina");
+            let ciphertext = key_pair.public.encrypt(&message).unwrap();
+
+            for (bits, expected_rounds) in [(2, 128), (3, 86), (8, 32)].iter() {
+                let mut queries = 0;
+                let recovered = low_bits_attack(
+                    &ciphertext,
+                    &key_pair.public,
+                    *bits,
+                    |c| {
+                        queries += 1;
+                        low_bits(&key_pair, *bits, c)
+                    },
+                    |_| {},
+                );
+
+                assert_eq!(Some(message.clone()), recovered);
+                assert_eq!(*expected_rounds, queries);
+            }
+        }
+
+        #[test]
+        fn handles_edge_plaintexts() {
+            let key_pair = key_pair(128, 48);
+            let n = &key_pair.public.n;
+
+            for message in [big(0), big(1), n - 1u32, n >> 1].iter() {
+                let ciphertext = key_pair.public.encrypt(message).unwrap();
+                let recovered = low_bits_attack(
+                    &ciphertext,
+                    &key_pair.public,
+                    4,
+                    |c| low_bits(&key_pair, 4, c),
+                    |_| {},
+                );
+
+                assert_eq!(Some(message.clone()), recovered);
+            }
+        }
+
+        #[test]
+        fn gives_up_when_the_oracle_does() {
+            let key_pair = key_pair(64, 49);
+            let ciphertext = key_pair.public.encrypt(&big(42)).unwrap();
+
+            assert_eq!(
+                None,
+                parity_attack(&ciphertext, &key_pair.public, |_| None, |_| {})
+            );
+            assert_eq!(
+                None,
+                low_bits_attack(&ciphertext, &key_pair.public, 0, |_| Some(0), |_| {})
+            );
+        }
+    }
+}
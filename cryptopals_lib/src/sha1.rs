@@ -0,0 +1,152 @@
+use super::hash::{self, Compression, Endian, MerkleDamgard};
+
+pub struct Sha1Compression;
+
+pub type Sha1 = MerkleDamgard<Sha1Compression>;
+
+impl Compression for Sha1Compression {
+    type State = [u32; 5];
+
+    const INITIAL_STATE: Self::State = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    const ENDIAN: Endian = Endian::Big;
+    const OUTPUT_WORDS: usize = 5;
+
+    fn compress(state: &mut Self::State, block: &[u8]) {
+        let mut w = [0u32; 80];
+        w[..16].copy_from_slice(&Self::ENDIAN.read_block(block));
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = *state;
+
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (word, value) in state.iter_mut().zip([a, b, c, d, e].iter()) {
+            *word = word.wrapping_add(*value);
+        }
+    }
+}
+
+pub fn digest(data: &[u8]) -> Vec<u8> {
+    let mut sha1 = Sha1::new();
+    sha1.update(data);
+    sha1.finalize()
+}
+
+pub fn md_padding(length: u64) -> Vec<u8> {
+    hash::md_padding::<Sha1Compression>(length)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hex;
+
+    mod digest {
+        use super::*;
+
+        #[test]
+        fn fips_180_vectors() {
+            assert_eq!(
+                b"da39a3ee5e6b4b0d3255bfef95601890afd80709".to_vec(),
+                hex::encode(&digest(b""))
+            );
+            assert_eq!(
+                b"a9993e364706816aba3e25717850c26c9cd0d89d".to_vec(),
+                hex::encode(&digest(b"abc"))
+            );
+            assert_eq!(
+                b"84983e441c3bd26ebaae4aa1f95129e5e54670f1".to_vec(),
+                hex::encode(&digest(
+                    b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+                ))
+            );
+        }
+
+        #[test]
+        fn one_million_a() {
+            let mut sha1 = Sha1::new();
+            (0..1000).for_each(|_| sha1.update(&[b'a'; 1000]));
+
+            assert_eq!(
+                b"34aa973cd4c4daa4f61eeb2bdbad27316534016f".to_vec(),
+                hex::encode(&sha1.finalize())
+            );
+        }
+    }
+
+    mod update {
+        use super::*;
+
+        #[test]
+        fn streaming_matches_one_shot() {
+            let data = b"The quick brown fox jumps over the lazy dog, again and again and again!";
+
+            for chunk_size in 1..data.len() {
+                let mut sha1 = Sha1::new();
+                data.chunks(chunk_size).for_each(|chunk| sha1.update(chunk));
+
+                assert_eq!(digest(data), sha1.finalize());
+            }
+        }
+    }
+
+    mod from_state {
+        use super::*;
+
+        #[test]
+        fn resumes_after_padded_message() {
+            let message = b"comment1=cooking%20MCs;userdata=foo";
+            let suffix = b";admin=true";
+            let glued = [message.to_vec(), md_padding(message.len() as u64)].concat();
+
+            let mut original = Sha1::new();
+            original.update(&glued);
+            let mut resumed = Sha1::from_state(original.state(), glued.len() as u64);
+            resumed.update(suffix);
+
+            assert_eq!(
+                digest(&[glued.as_slice(), suffix].concat()),
+                resumed.finalize()
+            );
+        }
+    }
+
+    mod md_padding {
+        use super::*;
+
+        #[test]
+        fn pads_to_block_boundary() {
+            for length in 0..200 {
+                let padding = md_padding(length);
+
+                assert_eq!(0, (length as usize + padding.len()) % 64);
+                assert_eq!(0x80, padding[0]);
+                assert_eq!(
+                    (length * 8).to_be_bytes().to_vec(),
+                    padding[padding.len() - 8..].to_vec()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use super::hash::{self, Compression, Endian, MerkleDamgard};
+
+pub struct Sha256Compression;
+pub struct Sha224Compression;
+
+pub type Sha256 = MerkleDamgard<Sha256Compression>;
+pub type Sha224 = MerkleDamgard<Sha224Compression>;
+
+impl Compression for Sha256Compression {
+    type State = [u32; 8];
+
+    const INITIAL_STATE: Self::State = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+    const ENDIAN: Endian = Endian::Big;
+    const OUTPUT_WORDS: usize = 8;
+
+    fn compress(state: &mut Self::State, block: &[u8]) {
+        compress(state, block)
+    }
+}
+
+impl Compression for Sha224Compression {
+    type State = [u32; 8];
+
+    const INITIAL_STATE: Self::State = [
+        0xc1059ed8, 0x367cd507, 0x3070dd17, 0xf70e5939, 0xffc00b31, 0x68581511, 0x64f98fa7,
+        0xbefa4fa4,
+    ];
+    const ENDIAN: Endian = Endian::Big;
+    const OUTPUT_WORDS: usize = 7;
+
+    fn compress(state: &mut Self::State, block: &[u8]) {
+        compress(state, block)
+    }
+}
+
+#[rustfmt::skip]
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+fn compress(state: &mut [u32; 8], block: &[u8]) {
+    let mut w = [0u32; 64];
+    w[..16].copy_from_slice(&Endian::Big.read_block(block));
+    for i in 16..64 {
+        let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+        w[i] = w[i - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[i - 7])
+            .wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+
+    for (k, word) in K.iter().zip(w.iter()) {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let ch = (e & f) ^ (!e & g);
+        let temp1 = h
+            .wrapping_add(s1)
+            .wrapping_add(ch)
+            .wrapping_add(*k)
+            .wrapping_add(*word);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let temp2 = s0.wrapping_add(maj);
+
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(temp1);
+        d = c;
+        c = b;
+        b = a;
+        a = temp1.wrapping_add(temp2);
+    }
+
+    for (word, value) in state.iter_mut().zip([a, b, c, d, e, f, g, h].iter()) {
+        *word = word.wrapping_add(*value);
+    }
+}
+
+pub fn digest(data: &[u8]) -> Vec<u8> {
+    let mut sha256 = Sha256::new();
+    sha256.update(data);
+    sha256.finalize()
+}
+
+pub fn digest_224(data: &[u8]) -> Vec<u8> {
+    let mut sha224 = Sha224::new();
+    sha224.update(data);
+    sha224.finalize()
+}
+
+pub fn md_padding(length: u64) -> Vec<u8> {
+    hash::md_padding::<Sha256Compression>(length)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hex;
+
+    #[test]
+    fn fips_180_vectors() {
+        let vectors: Vec<(&[u8], &[u8], &[u8])> = vec![
+            (
+                b"",
+                b"e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+                b"d14a028c2a3a2bc9476102bb288234c415a2b01f828ea62ac5b3e42f",
+            ),
+            (
+                b"abc",
+                b"ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+                b"23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7",
+            ),
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                b"248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+                b"75388b16512776cc5dba5da1fd890150b0c6455cb4f58b1952522525",
+            ),
+        ];
+
+        for (message, expected_256, expected_224) in vectors {
+            assert_eq!(expected_256.to_vec(), hex::encode(&digest(message)));
+            assert_eq!(expected_224.to_vec(), hex::encode(&digest_224(message)));
+        }
+    }
+
+    #[test]
+    fn one_million_a() {
+        let mut sha256 = Sha256::new();
+        let mut sha224 = Sha224::new();
+        for _ in 0..1000 {
+            sha256.update(&[b'a'; 1000]);
+            sha224.update(&[b'a'; 1000]);
+        }
+
+        assert_eq!(
+            b"cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0".to_vec(),
+            hex::encode(&sha256.finalize())
+        );
+        assert_eq!(
+            b"20794655980c91d8bbb4c1ea97618a4bf03f42581948b2ee4ee7ad67".to_vec(),
+            hex::encode(&sha224.finalize())
+        );
+    }
+
+    #[test]
+    fn length_extension() {
+        use crate::mac::{length_extension, secret_prefix_mac, verify_secret_prefix_mac};
+
+        let key = b"YELLOW SUBMARINE";
+        let message = b"comment1=cooking%20MCs;userdata=foo";
+        let mac = secret_prefix_mac::<Sha256>(key, message);
+        let verify =
+            |message: &[u8], mac: &[u8]| verify_secret_prefix_mac::<Sha256>(key, message, mac);
+
+        let forged = length_extension::<Sha256, _>(&mac, message, b";admin=true", 0..32, verify);
+        assert!(forged.is_some())
+    }
+}
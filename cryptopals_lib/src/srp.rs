@@ -0,0 +1,684 @@
+use super::bignum::{self, BigUint};
+use super::dh::{Group, KeyPair};
+use super::hmac::{hmac, verify_hmac};
+use super::network::Party;
+use super::sha256::{self, Sha256};
+use rand::Rng;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+pub const SALT_SIZE: usize = 16;
+
+// What the server stores in place of the password.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Registration {
+    pub salt: Vec<u8>,
+    pub verifier: BigUint,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SrpMessage {
+    Hello {
+        username: Vec<u8>,
+        public: BigUint,
+    },
+    Challenge {
+        salt: Vec<u8>,
+        public: BigUint,
+    },
+    // Simplified SRP drops the multiplier and lets the server pick u.
+    SimplifiedChallenge {
+        salt: Vec<u8>,
+        public: BigUint,
+        scrambler: BigUint,
+    },
+    Proof(Vec<u8>),
+    Ok,
+    Rejected,
+}
+
+// Left pads to the byte length of N, as SRP-6a hashes fixed width values. Unreduced values
+// from a misbehaving peer are hashed as they are.
+fn pad(group: &Group, n: &BigUint) -> Vec<u8> {
+    let length = bignum::bits(&group.p).max(bignum::bits(n)).div_ceil(8) as usize;
+    bignum::to_bytes_be_padded(n, length)
+}
+
+fn hash_to_int(data: &[u8]) -> BigUint {
+    bignum::from_bytes_be(&sha256::digest(data))
+}
+
+// k = H(N | PAD(g))
+pub fn multiplier(group: &Group) -> BigUint {
+    let n = bignum::to_bytes_be(&group.p);
+    hash_to_int(&[n, pad(group, &group.g)].concat())
+}
+
+// x = H(s | H(I | ":" | P))
+pub fn private_key(salt: &[u8], username: &[u8], password: &[u8]) -> BigUint {
+    let identity = sha256::digest(&[username, b":", password].concat());
+    hash_to_int(&[salt, &identity].concat())
+}
+
+// x = H(s | P), as simplified SRP doesn't mix in the username.
+pub fn simplified_private_key(salt: &[u8], password: &[u8]) -> BigUint {
+    hash_to_int(&[salt, password].concat())
+}
+
+// u = H(PAD(A) | PAD(B))
+pub fn scrambler(group: &Group, client_public: &BigUint, server_public: &BigUint) -> BigUint {
+    hash_to_int(&[pad(group, client_public), pad(group, server_public)].concat())
+}
+
+pub fn session_key(secret: &BigUint) -> Vec<u8> {
+    sha256::digest(&bignum::to_bytes_be(secret))
+}
+
+pub fn proof(key: &[u8], salt: &[u8]) -> Vec<u8> {
+    hmac::<Sha256>(key, salt)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Variant {
+    Srp6a,
+    Simplified,
+}
+
+pub fn register<R: Rng>(
+    group: &Group,
+    variant: Variant,
+    username: &[u8],
+    password: &[u8],
+    rng: &mut R,
+) -> Registration {
+    let salt: Vec<u8> = (0..SALT_SIZE).map(|_| rng.gen::<u8>()).collect();
+    let x = match variant {
+        Variant::Srp6a => private_key(&salt, username, password),
+        Variant::Simplified => simplified_private_key(&salt, password),
+    };
+    let verifier = bignum::modpow(&group.g, &x, &group.p);
+
+    Registration { salt, verifier }
+}
+
+// B = k * v + g^b
+pub fn server_public_key(group: &Group, verifier: &BigUint, key_pair: &KeyPair) -> BigUint {
+    (multiplier(group) * verifier + &key_pair.public) % &group.p
+}
+
+// S = (B - k * g^x)^(a + u * x)
+pub fn client_secret(
+    group: &Group,
+    key_pair: &KeyPair,
+    server_public: &BigUint,
+    x: &BigUint,
+    u: &BigUint,
+) -> BigUint {
+    let p = &group.p;
+    let kgx = multiplier(group) * bignum::modpow(&group.g, x, p) % p;
+    let base = (server_public % p + p - kgx) % p;
+
+    bignum::modpow(&base, &(&key_pair.private + u * x), p)
+}
+
+// S = B^(a + u * x)
+pub fn simplified_client_secret(
+    group: &Group,
+    key_pair: &KeyPair,
+    server_public: &BigUint,
+    x: &BigUint,
+    u: &BigUint,
+) -> BigUint {
+    bignum::modpow(server_public, &(&key_pair.private + u * x), &group.p)
+}
+
+// S = (A * v^u)^b
+pub fn server_secret(
+    group: &Group,
+    key_pair: &KeyPair,
+    client_public: &BigUint,
+    verifier: &BigUint,
+    u: &BigUint,
+) -> BigUint {
+    let p = &group.p;
+    let base = client_public * bignum::modpow(verifier, u, p) % p;
+
+    bignum::modpow(&base, &key_pair.private, p)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ClientState {
+    Start,
+    AwaitingChallenge,
+    AwaitingResult,
+    Done,
+}
+
+pub struct SrpClient {
+    group: Group,
+    username: Vec<u8>,
+    password: Vec<u8>,
+    key_pair: KeyPair,
+    state: ClientState,
+    pub authenticated: Option<bool>,
+}
+
+impl SrpClient {
+    pub fn new<R: Rng>(group: Group, username: &[u8], password: &[u8], rng: &mut R) -> Self {
+        let key_pair = KeyPair::generate(&group, rng);
+
+        Self {
+            group,
+            username: username.to_vec(),
+            password: password.to_vec(),
+            key_pair,
+            state: ClientState::Start,
+            authenticated: None,
+        }
+    }
+}
+
+impl Party<SrpMessage> for SrpClient {
+    fn start(&mut self) -> Vec<SrpMessage> {
+        self.state = ClientState::AwaitingChallenge;
+
+        vec![SrpMessage::Hello {
+            username: self.username.clone(),
+            public: self.key_pair.public.clone(),
+        }]
+    }
+
+    fn receive(&mut self, message: SrpMessage) -> Vec<SrpMessage> {
+        match (self.state, message) {
+            (ClientState::AwaitingChallenge, SrpMessage::Challenge { salt, public }) => {
+                let x = private_key(&salt, &self.username, &self.password);
+                let u = scrambler(&self.group, &self.key_pair.public, &public);
+                let secret = client_secret(&self.group, &self.key_pair, &public, &x, &u);
+
+                self.state = ClientState::AwaitingResult;
+                vec![SrpMessage::Proof(proof(&session_key(&secret), &salt))]
+            }
+            (
+                ClientState::AwaitingChallenge,
+                SrpMessage::SimplifiedChallenge {
+                    salt,
+                    public,
+                    scrambler,
+                },
+            ) => {
+                let x = simplified_private_key(&salt, &self.password);
+                let secret =
+                    simplified_client_secret(&self.group, &self.key_pair, &public, &x, &scrambler);
+
+                self.state = ClientState::AwaitingResult;
+                vec![SrpMessage::Proof(proof(&session_key(&secret), &salt))]
+            }
+            (ClientState::AwaitingResult, SrpMessage::Ok) => {
+                self.state = ClientState::Done;
+                self.authenticated = Some(true);
+                vec![]
+            }
+            (_, SrpMessage::Rejected) => {
+                self.state = ClientState::Done;
+                self.authenticated = Some(false);
+                vec![]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+struct Session {
+    username: Vec<u8>,
+    key: Vec<u8>,
+    salt: Vec<u8>,
+}
+
+pub struct SrpServer<R: Rng> {
+    rng: R,
+    group: Group,
+    variant: Variant,
+    validate: bool,
+    users: HashMap<Vec<u8>, Registration>,
+    session: Option<Session>,
+    pub authenticated: Vec<Vec<u8>>,
+}
+
+impl<R: Rng> SrpServer<R> {
+    pub fn new(group: Group, rng: R) -> Self {
+        Self::with_variant(group, Variant::Srp6a, rng)
+    }
+
+    pub fn simplified(group: Group, rng: R) -> Self {
+        Self::with_variant(group, Variant::Simplified, rng)
+    }
+
+    fn with_variant(group: Group, variant: Variant, rng: R) -> Self {
+        Self {
+            rng,
+            group,
+            variant,
+            validate: true,
+            users: HashMap::new(),
+            session: None,
+            authenticated: Vec::new(),
+        }
+    }
+
+    // Client public keys that are 0 mod N pin the secret to 0, so they are refused unless
+    // this is called. Only useful for demonstrating that attack.
+    pub fn skip_validation(mut self) -> Self {
+        self.validate = false;
+        self
+    }
+
+    pub fn register(&mut self, username: &[u8], password: &[u8]) {
+        let registration = register(&self.group, self.variant, username, password, &mut self.rng);
+        self.users.insert(username.to_vec(), registration);
+    }
+
+    fn challenge(&mut self, username: Vec<u8>, client_public: &BigUint) -> Vec<SrpMessage> {
+        let registration = match self.users.get(&username) {
+            Some(registration) => registration.clone(),
+            None => return vec![SrpMessage::Rejected],
+        };
+        if self.validate && (client_public % &self.group.p) == BigUint::from(0u32) {
+            return vec![SrpMessage::Rejected];
+        }
+
+        let key_pair = KeyPair::generate(&self.group, &mut self.rng);
+        let (public, u) = match self.variant {
+            Variant::Srp6a => {
+                let public = server_public_key(&self.group, &registration.verifier, &key_pair);
+                let u = scrambler(&self.group, client_public, &public);
+                (public, u)
+            }
+            Variant::Simplified => {
+                let u = bignum::from_bytes_be(&self.rng.gen::<[u8; 16]>());
+                (key_pair.public.clone(), u)
+            }
+        };
+        let secret = server_secret(
+            &self.group,
+            &key_pair,
+            client_public,
+            &registration.verifier,
+            &u,
+        );
+
+        self.session = Some(Session {
+            username,
+            key: session_key(&secret),
+            salt: registration.salt.clone(),
+        });
+
+        match self.variant {
+            Variant::Srp6a => vec![SrpMessage::Challenge {
+                salt: registration.salt,
+                public,
+            }],
+            Variant::Simplified => vec![SrpMessage::SimplifiedChallenge {
+                salt: registration.salt,
+                public,
+                scrambler: u,
+            }],
+        }
+    }
+}
+
+impl<R: Rng> Party<SrpMessage> for SrpServer<R> {
+    fn receive(&mut self, message: SrpMessage) -> Vec<SrpMessage> {
+        match message {
+            SrpMessage::Hello { username, public } => self.challenge(username, &public),
+            SrpMessage::Proof(mac) => match self.session.take() {
+                Some(session) if verify_hmac::<Sha256>(&session.key, &session.salt, &mac) => {
+                    self.authenticated.push(session.username);
+                    vec![SrpMessage::Ok]
+                }
+                _ => vec![SrpMessage::Rejected],
+            },
+            _ => vec![],
+        }
+    }
+}
+
+// Challenge 37: logs in without the password by sending A = 0, N, 2N, ... so the server's
+// secret (A * v^u)^b is always 0.
+pub struct ZeroKeyClient {
+    username: Vec<u8>,
+    public: BigUint,
+    pub authenticated: Option<bool>,
+}
+
+impl ZeroKeyClient {
+    pub fn new(group: &Group, username: &[u8], multiple: u32) -> Self {
+        Self {
+            username: username.to_vec(),
+            public: &group.p * multiple,
+            authenticated: None,
+        }
+    }
+}
+
+impl Party<SrpMessage> for ZeroKeyClient {
+    fn start(&mut self) -> Vec<SrpMessage> {
+        vec![SrpMessage::Hello {
+            username: self.username.clone(),
+            public: self.public.clone(),
+        }]
+    }
+
+    fn receive(&mut self, message: SrpMessage) -> Vec<SrpMessage> {
+        match message {
+            SrpMessage::Challenge { salt, .. } | SrpMessage::SimplifiedChallenge { salt, .. } => {
+                let key = session_key(&BigUint::from(0u32));
+                vec![SrpMessage::Proof(proof(&key, &salt))]
+            }
+            SrpMessage::Ok => {
+                self.authenticated = Some(true);
+                vec![]
+            }
+            SrpMessage::Rejected => {
+                self.authenticated = Some(false);
+                vec![]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+// Everything a simplified SRP server sees during one login, which is enough to test
+// password guesses offline.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CapturedLogin {
+    pub client_public: BigUint,
+    pub salt: Vec<u8>,
+    pub server_key_pair: KeyPair,
+    pub scrambler: BigUint,
+    pub proof: Vec<u8>,
+}
+
+impl CapturedLogin {
+    pub fn check_password(&self, group: &Group, password: &[u8]) -> bool {
+        let x = simplified_private_key(&self.salt, password);
+        let verifier = bignum::modpow(&group.g, &x, &group.p);
+        let secret = server_secret(
+            group,
+            &self.server_key_pair,
+            &self.client_public,
+            &verifier,
+            &self.scrambler,
+        );
+
+        verify_hmac::<Sha256>(&session_key(&secret), &self.salt, &self.proof)
+    }
+}
+
+// Challenge 38: poses as a simplified SRP server with b = 1 and u = 1, which makes every
+// password guess cost a single exponentiation.
+pub struct DictionaryServer {
+    group: Group,
+    salt: Vec<u8>,
+    client_public: Option<BigUint>,
+    pub captured: Option<CapturedLogin>,
+}
+
+impl DictionaryServer {
+    pub fn new(group: Group) -> Self {
+        Self {
+            group,
+            salt: Vec::new(),
+            client_public: None,
+            captured: None,
+        }
+    }
+}
+
+impl Party<SrpMessage> for DictionaryServer {
+    fn receive(&mut self, message: SrpMessage) -> Vec<SrpMessage> {
+        match message {
+            SrpMessage::Hello { public, .. } => {
+                self.client_public = Some(public);
+                vec![SrpMessage::SimplifiedChallenge {
+                    salt: self.salt.clone(),
+                    public: self.group.g.clone(),
+                    scrambler: BigUint::from(1u32),
+                }]
+            }
+            SrpMessage::Proof(proof) => match self.client_public.take() {
+                Some(client_public) => {
+                    self.captured = Some(CapturedLogin {
+                        client_public,
+                        salt: self.salt.clone(),
+                        server_key_pair: KeyPair {
+                            private: BigUint::from(1u32),
+                            public: self.group.g.clone(),
+                        },
+                        scrambler: BigUint::from(1u32),
+                        proof,
+                    });
+                    vec![SrpMessage::Ok]
+                }
+                None => vec![SrpMessage::Rejected],
+            },
+            _ => vec![],
+        }
+    }
+}
+
+pub fn read_wordlist<P: AsRef<Path>>(path: P) -> io::Result<Vec<Vec<u8>>> {
+    let words = fs::read_to_string(path)?
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.as_bytes().to_vec())
+        .collect();
+
+    Ok(words)
+}
+
+// Splits the wordlist across `threads` workers, which all stop once any of them hits.
+pub fn crack_password(
+    group: &Group,
+    captured: &CapturedLogin,
+    wordlist: &[Vec<u8>],
+    threads: usize,
+) -> Option<Vec<u8>> {
+    if wordlist.is_empty() {
+        return None;
+    }
+
+    let chunk_size = wordlist.len().div_ceil(threads.max(1));
+    let found = AtomicBool::new(false);
+    let password = Mutex::new(None);
+
+    thread::scope(|scope| {
+        for chunk in wordlist.chunks(chunk_size) {
+            let (found, password) = (&found, &password);
+            scope.spawn(move || {
+                for word in chunk {
+                    if found.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    if captured.check_password(group, word) {
+                        found.store(true, Ordering::Relaxed);
+                        *password.lock().unwrap() = Some(word.clone());
+                        return;
+                    }
+                }
+            });
+        }
+    });
+
+    password.into_inner().unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::network::{self, Passthrough};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn small_group() -> Group {
+        let p = BigUint::from(1019u32);
+        Group::new(p, BigUint::from(2u32), Some(BigUint::from(509u32)))
+    }
+
+    fn login(group: Group, password: &[u8], seed: u64) -> (SrpClient, SrpServer<StdRng>) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut server = SrpServer::new(group.clone(), StdRng::seed_from_u64(seed + 1));
+        server.register(b"alice@example.com", b"hunter2");
+
+        let mut client = SrpClient::new(group, b"alice@example.com", password, &mut rng);
+        network::run(&mut client, &mut server, &mut Passthrough, 10);
+
+        (client, server)
+    }
+
+    #[test]
+    fn secrets_agree() {
+        let group = Group::nist();
+        let mut rng = StdRng::seed_from_u64(36);
+        let registration = register(&group, Variant::Srp6a, b"user", b"password", &mut rng);
+
+        let client = KeyPair::generate(&group, &mut rng);
+        let server = KeyPair::generate(&group, &mut rng);
+        let server_public = server_public_key(&group, &registration.verifier, &server);
+        let u = scrambler(&group, &client.public, &server_public);
+        let x = private_key(&registration.salt, b"user", b"password");
+
+        assert_eq!(
+            client_secret(&group, &client, &server_public, &x, &u),
+            server_secret(&group, &server, &client.public, &registration.verifier, &u)
+        );
+    }
+
+    #[test]
+    fn small_group_login() {
+        for seed in 0..20 {
+            let (client, server) = login(small_group(), b"hunter2", seed);
+
+            assert_eq!(Some(true), client.authenticated);
+            assert_eq!(vec![b"alice@example.com".to_vec()], server.authenticated);
+        }
+    }
+
+    #[test]
+    fn wrong_password_is_rejected() {
+        let (client, server) = login(Group::nist(), b"hunter3", 1);
+
+        assert_eq!(Some(false), client.authenticated);
+        assert!(server.authenticated.is_empty());
+    }
+
+    #[test]
+    fn unknown_user_is_rejected() {
+        let group = small_group();
+        let mut server = SrpServer::new(group.clone(), StdRng::seed_from_u64(2));
+        let mut client = SrpClient::new(group, b"mallory", b"", &mut StdRng::seed_from_u64(3));
+
+        network::run(&mut client, &mut server, &mut Passthrough, 10);
+
+        assert_eq!(Some(false), client.authenticated);
+        assert!(server.authenticated.is_empty());
+    }
+
+    #[test]
+    fn values_are_padded_to_modulus_width() {
+        let group = small_group();
+
+        assert_eq!(vec![0, 2], pad(&group, &group.g));
+        assert_eq!(vec![7, 246], pad(&group, &(&group.p * 2u32)));
+        assert_eq!(hash_to_int(&[3, 251, 0, 2]), multiplier(&group));
+    }
+
+    mod attacks {
+        use super::*;
+
+        fn server(group: &Group, seed: u64) -> SrpServer<StdRng> {
+            let mut server = SrpServer::new(group.clone(), StdRng::seed_from_u64(seed));
+            server.register(b"alice@example.com", b"hunter2");
+            server
+        }
+
+        #[test]
+        fn zero_key_bypasses_password() {
+            let group = small_group();
+
+            for multiple in 0..4 {
+                let mut client = ZeroKeyClient::new(&group, b"alice@example.com", multiple);
+                let mut server = server(&group, multiple as u64).skip_validation();
+
+                network::run(&mut client, &mut server, &mut Passthrough, 10);
+
+                assert_eq!(Some(true), client.authenticated);
+                assert_eq!(vec![b"alice@example.com".to_vec()], server.authenticated);
+            }
+        }
+
+        #[test]
+        fn server_refuses_zero_key_by_default() {
+            let group = small_group();
+            let mut client = ZeroKeyClient::new(&group, b"alice@example.com", 2);
+            let mut server = server(&group, 4);
+
+            network::run(&mut client, &mut server, &mut Passthrough, 10);
+
+            assert_eq!(Some(false), client.authenticated);
+            assert!(server.authenticated.is_empty());
+        }
+
+        #[test]
+        fn simplified_login() {
+            let group = small_group();
+
+            for (password, expected) in [(b"hunter2", true), (b"hunter3", false)].iter() {
+                let mut server = SrpServer::simplified(group.clone(), StdRng::seed_from_u64(5));
+                server.register(b"alice@example.com", b"hunter2");
+                let mut client = SrpClient::new(
+                    group.clone(),
+                    b"alice@example.com",
+                    *password,
+                    &mut StdRng::seed_from_u64(6),
+                );
+
+                network::run(&mut client, &mut server, &mut Passthrough, 10);
+
+                assert_eq!(Some(*expected), client.authenticated);
+            }
+        }
+
+        #[test]
+        fn dictionary_attack_on_simplified_srp() {
+            let group = Group::nist();
+            let wordlist: Vec<Vec<u8>> = ["letmein", "dragon", "monkey", "hunter2", "shadow"]
+                .iter()
+                .map(|word| word.as_bytes().to_vec())
+                .collect();
+            let mut client = SrpClient::new(
+                group.clone(),
+                b"alice@example.com",
+                b"monkey",
+                &mut StdRng::seed_from_u64(7),
+            );
+            let mut mallory = DictionaryServer::new(group.clone());
+
+            network::run(&mut client, &mut mallory, &mut Passthrough, 10);
+            let captured = mallory.captured.unwrap();
+
+            for threads in 1..=4 {
+                assert_eq!(
+                    Some(b"monkey".to_vec()),
+                    crack_password(&group, &captured, &wordlist, threads)
+                );
+            }
+            assert_eq!(None, crack_password(&group, &captured, &wordlist[..2], 2));
+        }
+    }
+}
@@ -0,0 +1,341 @@
+use super::heuristics::{median, trimmed_mean};
+use super::hex;
+use super::hmac::hmac;
+use super::sha1::Sha1;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+pub fn insecure_compare(a: &[u8], b: &[u8], delay: Duration) -> bool {
+    for (x, y) in a.iter().zip(b.iter()) {
+        if x != y {
+            return false;
+        }
+        thread::sleep(delay);
+    }
+
+    a.len() == b.len()
+}
+
+pub struct HmacServer {
+    addr: SocketAddr,
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl HmacServer {
+    // Serves `/test?file=...&signature=...` on an ephemeral loopback port, checking the
+    // signature against the first `signature_len` bytes of HMAC-SHA1(key, file).
+    pub fn start(key: &[u8], delay: Duration, signature_len: usize) -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let running = Arc::new(AtomicBool::new(true));
+
+        let key = key.to_vec();
+        let still_running = running.clone();
+        let handle = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if !still_running.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    let _ = handle_request(stream, &key, delay, signature_len);
+                }
+            }
+        });
+
+        Ok(Self {
+            addr,
+            running,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for HmacServer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        // Wake the accept loop so it notices the shutdown.
+        let _ = TcpStream::connect(self.addr);
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn handle_request(
+    mut stream: TcpStream,
+    key: &[u8],
+    delay: Duration,
+    signature_len: usize,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+
+    let status = match parse_request_line(&request_line) {
+        Some((file, signature)) => {
+            let mac = hmac::<Sha1>(key, file.as_bytes());
+            if insecure_compare(&signature, &mac[..signature_len], delay) {
+                "200 OK"
+            } else {
+                "500 Internal Server Error"
+            }
+        }
+        None => "400 Bad Request",
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        status
+    )
+}
+
+fn parse_request_line(request_line: &str) -> Option<(String, Vec<u8>)> {
+    let path = request_line.split_whitespace().nth(1)?;
+    let query = path.strip_prefix("/test?")?;
+
+    let mut file = None;
+    let mut signature = None;
+    for pair in query.split('&') {
+        let mut parts = pair.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some("file"), Some(value)) => file = Some(value.to_string()),
+            (Some("signature"), Some(value)) => signature = Some(value),
+            _ => (),
+        }
+    }
+
+    let signature = signature?;
+    if signature.len() % 2 != 0 || !signature.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    Some((file?, hex::decode(signature.as_bytes())))
+}
+
+pub fn request_signature(
+    addr: SocketAddr,
+    file: &str,
+    signature: &[u8],
+) -> io::Result<(bool, Duration)> {
+    let signature = String::from_utf8(hex::encode(signature)).unwrap();
+    let start = Instant::now();
+
+    let mut stream = TcpStream::connect(addr)?;
+    stream.set_nodelay(true)?;
+    write!(
+        stream,
+        "GET /test?file={}&signature={} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        file, signature, addr
+    )?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+
+    Ok((response.starts_with("HTTP/1.1 200"), start.elapsed()))
+}
+
+// A position that keeps coming out unclear gets its samples doubled at most this many times
+// before the recovery gives up.
+const MAX_SAMPLE_DOUBLINGS: u32 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Statistic {
+    Median,
+    TrimmedMean(f64),
+}
+
+impl Statistic {
+    pub fn apply(self, samples: &[f64]) -> f64 {
+        match self {
+            Statistic::Median => median(samples),
+            Statistic::TrimmedMean(trim) => trimmed_mean(samples, trim),
+        }
+    }
+}
+
+pub fn recover_signature(
+    addr: SocketAddr,
+    file: &str,
+    signature_len: usize,
+    samples: usize,
+    statistic: Statistic,
+) -> io::Result<Option<Vec<u8>>> {
+    let mut known = Vec::with_capacity(signature_len);
+    let mut rounds = vec![samples; signature_len];
+    let max_rounds = samples << MAX_SAMPLE_DOUBLINGS;
+
+    loop {
+        let position = known.len();
+        if position + 1 < signature_len {
+            let (byte, clear) = rank_next_byte(
+                addr,
+                file,
+                &known,
+                signature_len,
+                rounds[position],
+                statistic,
+            )?;
+            if clear {
+                known.push(byte);
+                continue;
+            }
+        } else if let Some(signature) = probe_last_byte(addr, file, &known)? {
+            return Ok(Some(signature));
+        }
+
+        // Either this position was too noisy to call or an earlier byte is wrong, which
+        // leaves every candidate taking the same time. Sample this position harder next time
+        // and re-check the byte before it.
+        let mut recheck = vec![];
+        if position + 1 < signature_len {
+            recheck.push(position);
+        }
+        if known.pop().is_some() {
+            recheck.push(position - 1);
+        }
+        if recheck.is_empty() {
+            return Ok(None);
+        }
+        for position in recheck {
+            rounds[position] *= 2;
+            if rounds[position] > max_rounds {
+                return Ok(None);
+            }
+        }
+    }
+}
+
+// The last byte doesn't need timing, the server tells us when it's right.
+fn probe_last_byte(addr: SocketAddr, file: &str, known: &[u8]) -> io::Result<Option<Vec<u8>>> {
+    for byte in 0..=255 {
+        let signature = [known, &[byte]].concat();
+        if request_signature(addr, file, &signature)?.0 {
+            return Ok(Some(signature));
+        }
+    }
+
+    Ok(None)
+}
+
+fn rank_next_byte(
+    addr: SocketAddr,
+    file: &str,
+    known: &[u8],
+    signature_len: usize,
+    samples: usize,
+    statistic: Statistic,
+) -> io::Result<(u8, bool)> {
+    let mut timings: Vec<Vec<f64>> = (0..256).map(|_| Vec::with_capacity(samples)).collect();
+    let mut signature = [known.to_vec(), vec![0; signature_len - known.len()]].concat();
+
+    // Sample in rounds over every candidate so drift in the environment hits them all alike.
+    for _ in 0..samples {
+        for (byte, timing) in timings.iter_mut().enumerate() {
+            signature[known.len()] = byte as u8;
+            let (_valid, elapsed) = request_signature(addr, file, &signature)?;
+            timing.push(elapsed.as_secs_f64());
+        }
+    }
+
+    let scores: Vec<f64> = timings
+        .iter()
+        .map(|timing| statistic.apply(timing))
+        .collect();
+    let best = scores
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).expect("Can't rank NaN timings"))
+        .map(|(byte, _score)| byte as u8)
+        .expect("No candidates were timed");
+
+    Ok((best, stands_out(&scores)))
+}
+
+// The right byte costs a whole extra comparison delay, so it should lead the runner-up by
+// more than the runner-up leads the typical candidate. Behind a wrong prefix every candidate
+// takes as long as the rest and the winner is just the luckiest draw.
+fn stands_out(scores: &[f64]) -> bool {
+    let mut sorted = scores.to_vec();
+    sorted.sort_by(|a, b| b.partial_cmp(a).expect("Can't rank NaN timings"));
+
+    match sorted.as_slice() {
+        [best, second, ..] => best - second > second - median(&sorted),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn server_checks_signature() {
+        let key = b"YELLOW SUBMARINE";
+        let server = HmacServer::start(key, Duration::from_millis(0), 20).unwrap();
+        let mac = hmac::<Sha1>(key, b"foo");
+
+        assert!(request_signature(server.addr(), "foo", &mac).unwrap().0);
+        assert!(!request_signature(server.addr(), "bar", &mac).unwrap().0);
+        assert!(
+            !request_signature(server.addr(), "foo", &mac[..19])
+                .unwrap()
+                .0
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_requests() {
+        assert_eq!(None, parse_request_line("GET /test?file=foo HTTP/1.1"));
+        assert_eq!(
+            None,
+            parse_request_line("GET /test?file=foo&signature=abc HTTP/1.1")
+        );
+        assert_eq!(
+            Some((String::from("foo"), vec![0xab, 0xcd])),
+            parse_request_line("GET /test?file=foo&signature=ABcd HTTP/1.1")
+        );
+    }
+
+    #[test]
+    fn clear_winner_stands_out() {
+        let mut scores = vec![1.0, 1.2, 1.19, 1.05, 0.95, 0.9, 1.1];
+        assert!(!stands_out(&scores));
+
+        scores[4] = 6.0;
+        assert!(stands_out(&scores));
+        assert!(!stands_out(&[6.0]));
+    }
+
+    #[test]
+    fn insecure_compare_exits_early() {
+        let delay = Duration::from_millis(5);
+
+        let start = Instant::now();
+        assert!(!insecure_compare(b"abcd", b"xbcd", delay));
+        let mismatch_first = start.elapsed();
+
+        let start = Instant::now();
+        assert!(!insecure_compare(b"abcd", b"abcx", delay));
+        let mismatch_last = start.elapsed();
+
+        assert!(mismatch_last > mismatch_first + delay * 2);
+    }
+}